@@ -0,0 +1,20 @@
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use rust_simple_redis::{RespDecode, RespEncode, RespError, RespFrame, RespParser};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BytesMut::from(data);
+    let ret = RespFrame::decode(&mut buf);
+    // once the scanner has delimited a frame, decoding it must not wait for more data
+    if let Ok(Some(_)) = RespParser::new().scan(data) {
+        assert_ne!(ret, Err(RespError::NotComplete));
+    }
+    if let Ok(frame) = ret {
+        // whatever decodes must encode back to something that decodes the same
+        let mut buf = BytesMut::from(&frame.clone().encode()[..]);
+        assert_eq!(RespFrame::decode(&mut buf).ok(), Some(frame));
+        assert!(buf.is_empty());
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use rust_simple_redis::RespParser;
+
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BytesMut::from(data);
+    let mut parser = RespParser::new();
+    while let Ok(Some(_)) = parser.parse_request(&mut buf) {}
+});
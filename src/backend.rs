@@ -0,0 +1,146 @@
+use std::{ops::Deref, sync::Arc};
+
+use dashmap::DashMap;
+
+use crate::{
+    cmd::{Command, CommandExecutor},
+    BulkString, Map, NullBulkString, RespFrame, SimpleString,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Backend(Arc<BackendInner>);
+
+#[derive(Debug, Default)]
+pub struct BackendInner {
+    pub(crate) map: DashMap<String, RespFrame>,
+    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
+}
+
+impl Deref for Backend {
+    type Target = BackendInner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Backend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<RespFrame> {
+        self.map.get(key).map(|v| v.value().clone())
+    }
+
+    pub fn set(&self, key: String, value: RespFrame) {
+        self.map.insert(key, value);
+    }
+
+    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
+        self.hmap
+            .get(key)
+            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    }
+
+    pub fn hset(&self, key: String, field: String, value: RespFrame) {
+        let hmap = self.hmap.entry(key).or_default();
+        hmap.insert(field, value);
+    }
+
+    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
+        self.hmap.get(key).map(|v| v.clone())
+    }
+}
+
+impl CommandExecutor for Backend {
+    fn execute(&self, cmd: Command) -> RespFrame {
+        match cmd {
+            Command::Get(get) => self.get(&get.key).unwrap_or_else(|| NullBulkString.into()),
+            Command::Set(set) => {
+                self.set(set.key, set.value);
+                SimpleString::new("OK").into()
+            }
+            Command::HGet(hget) => self
+                .hget(&hget.key, &hget.field)
+                .unwrap_or_else(|| NullBulkString.into()),
+            Command::HSet(hset) => {
+                self.hset(hset.key, hset.field, hset.value);
+                SimpleString::new("OK").into()
+            }
+            Command::HGetAll(hgetall) => match self.hgetall(&hgetall.key) {
+                // Map keeps its entries sorted by key
+                Some(hmap) => Map::new(
+                    hmap.into_iter()
+                        .map(|(k, v)| (BulkString::new(k).into(), v)),
+                )
+                .into(),
+                None => Map::new(vec![]).into(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::{Get, HGet, HGetAll, HSet, Set};
+
+    use super::*;
+
+    #[test]
+    fn test_set_get() {
+        let backend = Backend::new();
+        let ret = backend.execute(Command::Get(Get {
+            key: "hello".to_string(),
+        }));
+        assert_eq!(ret, NullBulkString.into());
+
+        let ret = backend.execute(Command::Set(Set {
+            key: "hello".to_string(),
+            value: BulkString::new("world").into(),
+        }));
+        assert_eq!(ret, SimpleString::new("OK").into());
+
+        let ret = backend.execute(Command::Get(Get {
+            key: "hello".to_string(),
+        }));
+        assert_eq!(ret, BulkString::new("world").into());
+    }
+
+    #[test]
+    fn test_hset_hget_hgetall() {
+        let backend = Backend::new();
+        let ret = backend.execute(Command::HGet(HGet {
+            key: "map".to_string(),
+            field: "hello".to_string(),
+        }));
+        assert_eq!(ret, NullBulkString.into());
+
+        for (field, value) in [("hello", "world"), ("foo", "bar")] {
+            let ret = backend.execute(Command::HSet(HSet {
+                key: "map".to_string(),
+                field: field.to_string(),
+                value: BulkString::new(value).into(),
+            }));
+            assert_eq!(ret, SimpleString::new("OK").into());
+        }
+
+        let ret = backend.execute(Command::HGet(HGet {
+            key: "map".to_string(),
+            field: "hello".to_string(),
+        }));
+        assert_eq!(ret, BulkString::new("world").into());
+
+        let ret = backend.execute(Command::HGetAll(HGetAll {
+            key: "map".to_string(),
+        }));
+        let expected = vec![
+            (BulkString::new("foo").into(), BulkString::new("bar").into()),
+            (
+                BulkString::new("hello").into(),
+                BulkString::new("world").into(),
+            ),
+        ];
+        assert_eq!(ret, Map::new(expected).into());
+    }
+}
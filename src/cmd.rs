@@ -20,6 +20,7 @@ pub trait CommandExecutor {
     fn execute(&self, cmd: Command) -> RespFrame;
 }
 
+#[derive(Debug)]
 pub enum Command {
     Set(Set),
     Get(Get),
@@ -60,8 +61,25 @@ pub struct HGetAll {
 impl TryFrom<Array> for Command {
     type Error = CommandError;
 
-    fn try_from(_value: Array) -> Result<Self, Self::Error> {
-        todo!()
+    fn try_from(value: Array) -> Result<Self, Self::Error> {
+        match value.first() {
+            Some(RespFrame::BulkString(ref cmd)) => {
+                match cmd.as_ref().to_ascii_lowercase().as_slice() {
+                    b"get" => Ok(Command::Get(Get::try_from(value)?)),
+                    b"set" => Ok(Command::Set(Set::try_from(value)?)),
+                    b"hget" => Ok(Command::HGet(HGet::try_from(value)?)),
+                    b"hset" => Ok(Command::HSet(HSet::try_from(value)?)),
+                    b"hgetall" => Ok(Command::HGetAll(HGetAll::try_from(value)?)),
+                    _ => Err(CommandError::InvalidCommand(format!(
+                        "unknown command {}",
+                        String::from_utf8_lossy(cmd.as_ref())
+                    ))),
+                }
+            }
+            _ => Err(CommandError::InvalidCommand(
+                "command must start with a BulkString".to_string(),
+            )),
+        }
     }
 }
 
@@ -102,3 +120,34 @@ fn validate_command(
 fn extract_args(value: Array, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use crate::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_command_dispatch() -> Result<(), Box<dyn std::error::Error>> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$3\r\nGeT\r\n$5\r\nhello\r\n");
+        let frame = Array::decode(&mut buf)?;
+
+        let cmd = Command::try_from(frame)?;
+        assert!(matches!(cmd, Command::Get(Get { ref key }) if key == "hello"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_dispatch_unknown() {
+        let frame = Array::new(vec![RespFrame::BulkString("foo".into())]);
+        let ret = Command::try_from(frame);
+        assert!(matches!(ret, Err(CommandError::InvalidCommand(_))));
+
+        let frame = Array::new(vec![]);
+        let ret = Command::try_from(frame);
+        assert!(matches!(ret, Err(CommandError::InvalidCommand(_))));
+    }
+}
@@ -15,8 +15,8 @@ impl TryFrom<Array> for HGet {
 
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => Ok(HGet {
-                key: String::from_utf8(key.0)?,
-                field: String::from_utf8(field.0)?,
+                key: String::from_utf8(key.to_vec())?,
+                field: String::from_utf8(field.to_vec())?,
             }),
             _ => Err(CommandError::InvalidArguments(
                 "Invalid key or field".to_string(),
@@ -34,7 +34,7 @@ impl TryFrom<Array> for HGetAll {
         let mut args = extract_args(_value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(HGetAll {
-                key: String::from_utf8(key.0)?,
+                key: String::from_utf8(key.to_vec())?,
             }),
             _ => Err(CommandError::InvalidArguments("Invalid key".to_string())),
         }
@@ -52,8 +52,8 @@ impl TryFrom<Array> for HSet {
         match (args.next(), args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
                 Ok(HSet {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
+                    key: String::from_utf8(key.to_vec())?,
+                    field: String::from_utf8(field.to_vec())?,
                     value,
                 })
             }
@@ -16,7 +16,7 @@ impl TryFrom<Array> for Get {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(Get {
-                key: String::from_utf8(key.0.clone())?,
+                key: String::from_utf8(key.to_vec())?,
             }),
             _ => Err(CommandError::InvalidArguments("invalid key".to_string())),
         }
@@ -33,7 +33,7 @@ impl TryFrom<Array> for Set {
 
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(value)) => Ok(Set {
-                key: String::from_utf8(key.0)?,
+                key: String::from_utf8(key.to_vec())?,
                 value,
             }),
             _ => Err(CommandError::InvalidArguments(
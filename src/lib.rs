@@ -1,4 +1,7 @@
+mod backend;
 pub mod cmd;
+pub mod network;
 mod resp;
 
+pub use backend::*;
 pub use resp::*;
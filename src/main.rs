@@ -1,3 +1,30 @@
-fn main() {
-    println!("Hello, world!");
+use anyhow::Result;
+use rust_simple_redis::{network, Backend};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+const DEFAULT_ADDR: &str = "0.0.0.0:6379";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    info!("Simple-Redis-Server is listening on {}", addr);
+    let listener = TcpListener::bind(&addr).await?;
+
+    let backend = Backend::new();
+    loop {
+        let (stream, raddr) = listener.accept().await?;
+        info!("Accepted connection from: {}", raddr);
+        let cloned_backend = backend.clone();
+        tokio::spawn(async move {
+            match network::stream_handler(stream, cloned_backend).await {
+                Ok(_) => info!("Connection from {} exited", raddr),
+                Err(e) => warn!("handle error for {}: {:?}", raddr, e),
+            }
+        });
+    }
 }
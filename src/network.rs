@@ -0,0 +1,207 @@
+use anyhow::Result;
+use bytes::BytesMut;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+use tracing::info;
+
+use crate::{
+    cmd::{Command, CommandExecutor},
+    Backend, DecodeLimits, RespEncode, RespFrame, RespParser, SimpleError,
+};
+
+#[derive(Debug, Default)]
+pub struct RespFrameCodec {
+    // keeps the scan progress of a partially received frame between reads
+    parser: RespParser,
+    // accept inline commands like `PING\r\n`, only clients send them
+    inline: bool,
+}
+
+impl RespFrameCodec {
+    pub fn new(limits: DecodeLimits) -> Self {
+        RespFrameCodec {
+            parser: RespParser::with_limits(limits),
+            inline: false,
+        }
+    }
+
+    /// A codec for the server side of a connection, which also accepts inline commands.
+    pub fn server(limits: DecodeLimits) -> Self {
+        RespFrameCodec {
+            inline: true,
+            ..Self::new(limits)
+        }
+    }
+}
+
+pub async fn stream_handler<S>(stream: S, backend: Backend) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut framed = Framed::new(stream, RespFrameCodec::server(DecodeLimits::default()));
+    while let Some(frame) = framed.next().await {
+        let frame = frame?;
+        info!("Received frame: {:?}", frame);
+        let resp = request_handler(frame, &backend);
+        // a reply that would desync the client is answered with an error instead, this
+        // is the only check before the encoder
+        let resp = match resp.validate() {
+            Ok(()) => resp,
+            Err(e) => SimpleError::try_new(format!("ERR {}", e))
+                .unwrap_or_else(|_| SimpleError::new("ERR invalid reply"))
+                .into(),
+        };
+        info!("Sending response: {:?}", resp);
+        framed.send(resp).await?;
+    }
+    Ok(())
+}
+
+fn request_handler(frame: RespFrame, backend: &Backend) -> RespFrame {
+    let cmd = match frame {
+        RespFrame::Array(array) => Command::try_from(array),
+        _ => {
+            return SimpleError::new("ERR command must be an Array").into();
+        }
+    };
+
+    match cmd {
+        Ok(cmd) => backend.execute(cmd),
+        // the message may echo client input, don't let it break the reply line
+        Err(e) => SimpleError::try_new(format!("ERR {}", e))
+            .unwrap_or_else(|_| SimpleError::new("ERR invalid command"))
+            .into(),
+    }
+}
+
+impl Encoder<RespFrame> for RespFrameCodec {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<()> {
+        item.encode_to(dst);
+        Ok(())
+    }
+}
+
+impl Decoder for RespFrameCodec {
+    type Item = RespFrame;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<RespFrame>> {
+        if self.inline {
+            Ok(self.parser.parse_request(src)?)
+        } else {
+            Ok(self.parser.parse(src)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    use crate::{Array, BulkString, Null, SimpleString};
+
+    use super::*;
+
+    const BUF_CAP: usize = 4096;
+
+    #[test]
+    fn test_codec_decode() -> Result<()> {
+        let mut codec = RespFrameCodec::default();
+        let mut buf = BytesMut::from(&b"+OK\r\n$5\r\nhel"[..]);
+
+        let frame = codec.decode(&mut buf)?;
+        assert_eq!(frame, Some(RespFrame::SimpleString("OK".into())));
+
+        assert_eq!(codec.decode(&mut buf)?, None);
+        buf.extend_from_slice(b"lo\r\n");
+        let frame = codec.decode(&mut buf)?;
+        assert_eq!(frame, Some(BulkString::new("hello").into()));
+
+        // a reply that is not RESP is a protocol error, not an inline command
+        let mut buf = BytesMut::from(&b"hello world\r\n"[..]);
+        assert!(codec.decode(&mut buf).is_err());
+
+        let mut codec = RespFrameCodec::server(DecodeLimits::default());
+        let mut buf = BytesMut::from(&b"get hello\r\n"[..]);
+        let frame = codec.decode(&mut buf)?;
+        let expected = Array::new(vec![
+            BulkString::new("get").into(),
+            BulkString::new("hello").into(),
+        ]);
+        assert_eq!(frame, Some(expected.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_encode() -> Result<()> {
+        let mut codec = RespFrameCodec::default();
+        let mut buf = BytesMut::new();
+        codec.encode(BulkString::new("hello").into(), &mut buf)?;
+        codec.encode(Null.into(), &mut buf)?;
+        assert_eq!(&buf[..], b"$5\r\nhello\r\n_\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_handler() -> Result<()> {
+        let (mut client, server) = duplex(BUF_CAP);
+        let handle = tokio::spawn(stream_handler(server, Backend::new()));
+
+        client
+            .write_all(b"*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n*2\r\n$3\r\nget\r\n$5\r\nhello\r\n")
+            .await?;
+        let expected = b"+OK\r\n$5\r\nworld\r\n";
+        let mut resp = vec![0; expected.len()];
+        client.read_exact(&mut resp).await?;
+        assert_eq!(resp, expected);
+
+        client.write_all(b"get 'hello'\r\n").await?;
+        let expected = b"$5\r\nworld\r\n";
+        let mut resp = vec![0; expected.len()];
+        client.read_exact(&mut resp).await?;
+        assert_eq!(resp, expected);
+
+        client.write_all(b"*1\r\n$3\r\nfoo\r\n").await?;
+        let expected = b"-ERR invalid command unknown command foo\r\n";
+        let mut resp = vec![0; expected.len()];
+        client.read_exact(&mut resp).await?;
+        assert_eq!(resp, expected);
+
+        drop(client);
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_handler_invalid_simple_line() -> Result<()> {
+        let backend = Backend::new();
+        let (mut client, server) = duplex(BUF_CAP);
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+
+        // a value with a bare LF is a protocol error and never gets stored
+        client
+            .write_all(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n+a\nb\r\n")
+            .await?;
+        assert!(handle.await?.is_err());
+        assert_eq!(backend.get("k"), None);
+
+        // a value that can't be encoded is answered with an error, the connection stays up
+        backend.set("k".to_string(), SimpleString("a\nb".to_string()).into());
+        let (mut client, server) = duplex(BUF_CAP);
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+        client
+            .write_all(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n*2\r\n$3\r\nget\r\n$1\r\nx\r\n")
+            .await?;
+        let expected = b"-ERR Simple frame can't contain CR or LF: \"a\\nb\"\r\n$-1\r\n";
+        let mut resp = vec![0; expected.len()];
+        client.read_exact(&mut resp).await?;
+        assert_eq!(resp, expected);
+
+        drop(client);
+        handle.await??;
+        Ok(())
+    }
+}
@@ -12,30 +12,68 @@
     - boolean: "#<t|f>\r\n"
     - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
     - big number: "([+|-]<number>\r\n"
+    - verbatim string: "=<length>\r\n<encoding>:<data>\r\n"
     - map: "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
     - set: "~<number-of-elements>\r\n<element-1>...<element-n>"
+    - push: "><number-of-elements>\r\n<element-1>...<element-n>"
+    - attribute: "|<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n><frame>"
+    - streamed string: "$?\r\n;<length>\r\n<data>\r\n...;0\r\n"
+    - streamed aggregate: "*?\r\n<element-1>...<element-n>.\r\n", also "%?" and "~?"
     - ...
 */
+mod convert;
+mod de;
 mod decode;
+mod display;
 mod encode;
+mod inline;
+mod json;
+mod parser;
+mod ser;
 
 use anyhow::Result;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use enum_dispatch::enum_dispatch;
 use std::{
-    collections::BTreeMap,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
     ops::{Deref, DerefMut},
+    str::FromStr,
 };
 use thiserror::Error;
 
+pub use convert::{FromRespFrame, ToRespArgs};
+pub use de::{from_bytes, from_frame};
+pub use display::RawFrame;
+pub use inline::decode_inline;
+pub use ordered_float::OrderedFloat;
+pub use parser::{DecodeLimits, RespParser, Utf8Mode};
+pub use ser::{to_bytes, to_frame, FrameSerializer};
+
 #[enum_dispatch]
 pub trait RespEncode {
-    fn encode(self) -> Vec<u8>;
+    /// Append the encoded frame to `buf`, nested frames are written in place.
+    fn encode_to(&self, buf: &mut BytesMut);
+
+    fn encode(self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        let mut buf = BytesMut::new();
+        self.encode_to(&mut buf);
+        buf.into()
+    }
 }
 
 #[enum_dispatch]
 pub trait RespDecode: Sized {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError>;
+    /// Decode the first frame in `buf` with the default `DecodeLimits`.
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        Self::decode_with(buf, &DecodeLimits::default())
+    }
+
+    /// Decode the first frame in `buf`, rejecting lengths and nesting beyond `limits`.
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError>;
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -48,17 +86,38 @@ pub enum RespError {
     InvalidFrameLength(isize),
     #[error("Frame is not complete")]
     NotComplete,
+    #[error("Bulk length {0} exceeds the limit")]
+    BulkLengthExceeded(usize),
+    #[error("Aggregate length {0} exceeds the limit")]
+    AggregateLengthExceeded(usize),
+    #[error("Nesting depth exceeds the limit {0}")]
+    NestingDepthExceeded(usize),
+    #[error("Inline command exceeds the limit {0}")]
+    InlineLengthExceeded(usize),
     #[error("Parse int error: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("Parse float error: {0}")]
     ParseFloatError(#[from] std::num::ParseFloatError),
     #[error("Utf8 error: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
+    #[error("Invalid utf8 in simple frame: {0}")]
+    InvalidUtf8(#[from] std::str::Utf8Error),
+    #[error("Simple frame can't contain CR or LF: {0:?}")]
+    InvalidSimpleLine(String),
+    #[error("Serde error: {0}")]
+    Serde(String),
+    #[error("Can't convert {found} into {expected}")]
+    InvalidConversion {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("Error reply: {0}")]
+    ErrorReply(String),
 }
 
 #[enum_dispatch(RespEncode)]
 // #[enum_dispatch(RespDecode)]
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 // #[enum_dispatch(RespDecode)]
 pub enum RespFrame {
     SimpleString(SimpleString),
@@ -71,32 +130,58 @@ pub enum RespFrame {
     NullArray(NullArray),
     Null(Null),
     Boolean(bool),
-    Double(f64),
+    // ordered-float semantics: NaN equals itself and sorts last, -0.0 equals 0.0
+    Double(OrderedFloat<f64>),
+    BigNumber(BigNumber),
+    VerbatimString(VerbatimString),
     Map(Map),
     Set(Set),
+    Push(Push),
+    Attributed(Attributed),
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct SimpleString(pub(crate) String);
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct SimpleError(pub(crate) String);
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct NullArray;
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Null;
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct NullBulkString;
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Array(pub(crate) Vec<RespFrame>);
-#[derive(Debug, PartialEq, PartialOrd)]
-pub struct Map(pub(crate) BTreeMap<String, RespFrame>);
-#[derive(Debug, PartialEq, PartialOrd)]
-pub struct Set(pub(crate) Vec<RespFrame>);
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Map(pub(crate) BTreeMap<RespFrame, RespFrame>);
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Set(pub(crate) BTreeSet<RespFrame>);
+// out-of-band data sent by the server, e.g. pub/sub messages
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Push(pub(crate) Vec<RespFrame>);
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Attribute(pub(crate) BTreeMap<RespFrame, RespFrame>);
+// a reply together with the attribute frame sent right before it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Attributed {
+    pub(crate) attributes: Attribute,
+    pub(crate) frame: Box<RespFrame>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct BulkError(pub(crate) String);
-#[derive(Debug, PartialEq, PartialOrd)]
+// decimal digits with an optional minus sign, validated and normalized on construction
+// so equal numbers have the same text, ordered by value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigNumber(pub(crate) String);
+// the format is a three bytes encoding hint such as `txt` or `mkd`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VerbatimString {
+    pub(crate) format: [u8; 3],
+    pub(crate) data: Vec<u8>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 // when encounter struct wrapper, we could impl Deref to access inner value as if it is the inner type
-pub struct BulkString(pub(crate) Vec<u8>);
+pub struct BulkString(pub(crate) Bytes);
 
 impl Deref for SimpleString {
     type Target = String;
@@ -131,7 +216,7 @@ impl Deref for Array {
 }
 
 impl Deref for Map {
-    type Target = BTreeMap<String, RespFrame>;
+    type Target = BTreeMap<RespFrame, RespFrame>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -144,7 +229,7 @@ impl DerefMut for Map {
 }
 
 impl Deref for Set {
-    type Target = Vec<RespFrame>;
+    type Target = BTreeSet<RespFrame>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -157,6 +242,58 @@ impl DerefMut for Set {
     }
 }
 
+impl Deref for Push {
+    type Target = Vec<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Push {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Deref for Attribute {
+    type Target = BTreeMap<RespFrame, RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Attribute {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Deref for Attributed {
+    type Target = RespFrame;
+
+    fn deref(&self) -> &Self::Target {
+        &self.frame
+    }
+}
+
+impl Deref for BigNumber {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Deref for VerbatimString {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
 impl Deref for BulkError {
     type Target = String;
     fn deref(&self) -> &Self::Target {
@@ -164,21 +301,90 @@ impl Deref for BulkError {
     }
 }
 
+impl RespFrame {
+    /// Whether the frame is a server-initiated push rather than a command reply.
+    pub fn is_push(&self) -> bool {
+        matches!(self, RespFrame::Push(_))
+    }
+
+    /// Check that the frame can be encoded without corrupting the stream, i.e. that no
+    /// simple string or simple error in it, however deeply nested, contains CR or LF.
+    pub fn validate(&self) -> Result<(), RespError> {
+        match self {
+            RespFrame::SimpleString(s) => check_simple_line(s),
+            RespFrame::Error(e) => check_simple_line(e),
+            RespFrame::Array(Array(frames)) | RespFrame::Push(Push(frames)) => {
+                frames.iter().try_for_each(RespFrame::validate)
+            }
+            RespFrame::Set(Set(frames)) => frames.iter().try_for_each(RespFrame::validate),
+            RespFrame::Map(Map(pairs)) => validate_pairs(pairs),
+            RespFrame::Attributed(attributed) => {
+                validate_pairs(&attributed.attributes)?;
+                attributed.frame.validate()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn validate_pairs(pairs: &BTreeMap<RespFrame, RespFrame>) -> Result<(), RespError> {
+    pairs.iter().try_for_each(|(key, value)| {
+        key.validate()?;
+        value.validate()
+    })
+}
+
+fn check_simple_line(s: &str) -> Result<(), RespError> {
+    if s.contains(['\r', '\n']) {
+        return Err(RespError::InvalidSimpleLine(s.to_string()));
+    }
+    Ok(())
+}
+
 impl SimpleString {
+    /// Build a simple string, see `try_new` for input that may contain CR or LF.
+    ///
+    /// # Panics
+    ///
+    /// If `s` contains CR or LF, which would end the line early and desync the stream.
     pub fn new(s: impl Into<String>) -> Self {
-        SimpleString(s.into())
+        Self::try_new(s).expect("simple string must not contain CR or LF")
+    }
+
+    /// Build a simple string, rejecting CR and LF which would end the line early.
+    pub fn try_new(s: impl Into<String>) -> Result<Self, RespError> {
+        let s = s.into();
+        check_simple_line(&s)?;
+        Ok(SimpleString(s))
     }
 }
 
 impl SimpleError {
+    /// Build a simple error, see `try_new` for input that may contain CR or LF.
+    ///
+    /// # Panics
+    ///
+    /// If `s` contains CR or LF, which would end the line early and desync the stream.
     pub fn new(s: impl Into<String>) -> Self {
-        SimpleError(s.into())
+        Self::try_new(s).expect("simple error must not contain CR or LF")
+    }
+
+    /// Build a simple error, rejecting CR and LF which would end the line early.
+    pub fn try_new(s: impl Into<String>) -> Result<Self, RespError> {
+        let s = s.into();
+        check_simple_line(&s)?;
+        Ok(SimpleError(s))
     }
 }
 
 impl BulkString {
     pub fn new(s: impl Into<Vec<u8>>) -> Self {
-        BulkString(s.into())
+        BulkString(s.into().into())
+    }
+
+    /// Wrap `Bytes` without copying, e.g. a slice of a read buffer.
+    pub fn from_bytes(bytes: Bytes) -> Self {
+        BulkString(bytes)
     }
 }
 
@@ -189,14 +395,109 @@ impl Array {
 }
 
 impl Map {
-    pub fn new(m: BTreeMap<String, RespFrame>) -> Self {
-        Map(m)
+    /// Build a map from key-value pairs, a later duplicate key replaces the earlier one.
+    pub fn new(m: impl IntoIterator<Item = (RespFrame, RespFrame)>) -> Self {
+        Map(m.into_iter().collect())
     }
 }
 
 impl Set {
+    /// Build a set from frames, duplicates are dropped.
+    pub fn new(s: impl IntoIterator<Item = RespFrame>) -> Self {
+        Set(s.into_iter().collect())
+    }
+}
+
+impl Push {
     pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
-        Set(s.into())
+        Push(s.into())
+    }
+}
+
+impl Attribute {
+    pub fn new(m: impl IntoIterator<Item = (RespFrame, RespFrame)>) -> Self {
+        Attribute(m.into_iter().collect())
+    }
+}
+
+impl Attributed {
+    pub fn new(attributes: Attribute, frame: impl Into<RespFrame>) -> Self {
+        Attributed {
+            attributes,
+            frame: Box::new(frame.into()),
+        }
+    }
+
+    pub fn attributes(&self) -> &Attribute {
+        &self.attributes
+    }
+
+    pub fn into_parts(self) -> (Attribute, RespFrame) {
+        (self.attributes, *self.frame)
+    }
+}
+
+impl BigNumber {
+    pub fn new(s: impl Into<String>) -> Result<Self, RespError> {
+        let s = s.into();
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(&s)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(RespError::InvalidFrame(format!(
+                "invalid big number {:?}",
+                s
+            )));
+        }
+
+        // `+5`, `005` and `5` are the same number, as are `-0` and `0`
+        let digits = digits.trim_start_matches('0');
+        let n = match digits {
+            "" => "0".to_string(),
+            digits if negative => format!("-{}", digits),
+            digits => digits.to_string(),
+        };
+        Ok(BigNumber(n))
+    }
+
+    fn sign_and_digits(&self) -> (bool, &str) {
+        match self.0.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, &self.0),
+        }
+    }
+}
+
+impl Ord for BigNumber {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // without leading zeros a longer magnitude is a larger one
+        let magnitude = |a: &str, b: &str| a.len().cmp(&b.len()).then_with(|| a.cmp(b));
+        match (self.sign_and_digits(), other.sign_and_digits()) {
+            ((false, a), (false, b)) => magnitude(a, b),
+            ((true, a), (true, b)) => magnitude(b, a),
+            ((true, _), (false, _)) => Ordering::Less,
+            ((false, _), (true, _)) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for BigNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl VerbatimString {
+    pub fn new(format: [u8; 3], data: impl Into<Vec<u8>>) -> Self {
+        VerbatimString {
+            format,
+            data: data.into(),
+        }
+    }
+
+    pub fn format(&self) -> &[u8; 3] {
+        &self.format
     }
 }
 
@@ -206,21 +507,28 @@ impl BulkError {
     }
 }
 
+impl From<f64> for RespFrame {
+    fn from(f: f64) -> Self {
+        RespFrame::Double(OrderedFloat(f))
+    }
+}
+
+// panics like `new` on CR or LF
 impl From<&str> for SimpleString {
     fn from(s: &str) -> Self {
-        SimpleString(s.into())
+        SimpleString::new(s)
     }
 }
 
 impl From<&str> for SimpleError {
     fn from(s: &str) -> Self {
-        SimpleError(s.into())
+        SimpleError::new(s)
     }
 }
 
 impl From<&str> for BulkString {
     fn from(s: &str) -> Self {
-        BulkString(s.into())
+        BulkString(Bytes::copy_from_slice(s.as_bytes()))
     }
 }
 
@@ -229,3 +537,41 @@ impl From<&str> for BulkError {
         BulkError(s.into())
     }
 }
+
+impl FromStr for BigNumber {
+    type Err = RespError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BigNumber::new(s)
+    }
+}
+
+macro_rules! impl_big_number_from_int {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for BigNumber {
+                fn from(n: $t) -> Self {
+                    BigNumber(n.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_big_number_from_int!(i64, u64, i128, u128);
+
+impl TryFrom<&BigNumber> for i128 {
+    type Error = RespError;
+
+    fn try_from(n: &BigNumber) -> Result<Self, Self::Error> {
+        Ok(n.0.parse()?)
+    }
+}
+
+impl TryFrom<&BigNumber> for i64 {
+    type Error = RespError;
+
+    fn try_from(n: &BigNumber) -> Result<Self, Self::Error> {
+        Ok(n.0.parse()?)
+    }
+}
@@ -0,0 +1,562 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::{BuildHasher, Hash},
+};
+
+use bytes::Bytes;
+
+use super::{Array, BulkString, RespError, RespFrame};
+
+/// Convert a reply into a Rust value.
+///
+/// Numbers and booleans are also parsed out of string replies, since Redis often returns
+/// them as bulk strings. Every null type converts into `None` or an empty collection,
+/// attributes are skipped, and error replies fail with `RespError::ErrorReply`. Anything
+/// else that doesn't fit fails with `RespError::InvalidConversion` naming the frame.
+pub trait FromRespFrame: Sized {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError>;
+}
+
+/// Turn a Rust value into command arguments.
+///
+/// Scalars are one argument each, collections and tuples are flattened into several and
+/// `None` into none. `u8` has no impl so that `Vec<u8>` isn't silently sent as a list of
+/// numbers, use `Bytes` for binary data.
+pub trait ToRespArgs {
+    /// Append the arguments this value stands for to `out`.
+    fn write_resp_args(&self, out: &mut Vec<BulkString>);
+
+    fn to_resp_args(&self) -> Vec<BulkString> {
+        let mut out = Vec::new();
+        self.write_resp_args(&mut out);
+        out
+    }
+
+    /// The arguments as a command ready to be sent, e.g. `("SET", "key", 1).to_command()`.
+    fn to_command(&self) -> Array {
+        let args = self.to_resp_args().into_iter().map(RespFrame::from);
+        Array::new(args.collect::<Vec<_>>())
+    }
+}
+
+impl FromRespFrame for RespFrame {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        Ok(frame)
+    }
+}
+
+macro_rules! impl_from_resp_frame_int {
+    ($($t:ty),*) => {
+        $(
+            impl FromRespFrame for $t {
+                fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+                    let frame = reply(frame)?;
+                    let n = match &frame {
+                        RespFrame::Integer(n) => <$t>::try_from(*n).ok(),
+                        RespFrame::BigNumber(n) => n.parse().ok(),
+                        _ => text(&frame).and_then(|s| s.parse().ok()),
+                    };
+                    n.ok_or_else(|| invalid(stringify!($t), &frame))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_resp_frame_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_from_resp_frame_float {
+    ($($t:ty),*) => {
+        $(
+            impl FromRespFrame for $t {
+                fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+                    let frame = reply(frame)?;
+                    let n = match &frame {
+                        RespFrame::Double(f) => Some(f.0 as $t),
+                        RespFrame::Integer(n) => Some(*n as $t),
+                        _ => text(&frame).and_then(|s| s.parse().ok()),
+                    };
+                    n.ok_or_else(|| invalid(stringify!($t), &frame))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_resp_frame_float!(f32, f64);
+
+impl FromRespFrame for bool {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        let frame = reply(frame)?;
+        match &frame {
+            RespFrame::Boolean(b) => Ok(*b),
+            RespFrame::Integer(n @ (0 | 1)) => Ok(*n == 1),
+            _ => match text(&frame) {
+                Some("1" | "true") => Ok(true),
+                Some("0" | "false") => Ok(false),
+                _ => Err(invalid("bool", &frame)),
+            },
+        }
+    }
+}
+
+impl FromRespFrame for String {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        let frame = reply(frame)?;
+        match &frame {
+            RespFrame::SimpleString(s) => Ok(s.0.clone()),
+            RespFrame::Integer(n) => Ok(n.to_string()),
+            RespFrame::Double(f) => Ok(f.to_string()),
+            RespFrame::BigNumber(n) => Ok(n.0.clone()),
+            RespFrame::VerbatimString(s) => {
+                String::from_utf8(s.data.clone()).map_err(|_| invalid("String", &frame))
+            }
+            _ => text(&frame)
+                .map(str::to_string)
+                .ok_or_else(|| invalid("String", &frame)),
+        }
+    }
+}
+
+impl FromRespFrame for Bytes {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        match reply(frame)? {
+            RespFrame::BulkString(s) => Ok(s.0),
+            RespFrame::SimpleString(s) => Ok(s.0.into()),
+            RespFrame::VerbatimString(s) => Ok(s.data.into()),
+            frame => Err(invalid("Bytes", &frame)),
+        }
+    }
+}
+
+impl<T: FromRespFrame> FromRespFrame for Option<T> {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        match reply(frame)? {
+            RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => Ok(None),
+            frame => T::from_resp_frame(frame).map(Some),
+        }
+    }
+}
+
+impl<T: FromRespFrame> FromRespFrame for Vec<T> {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        elements(frame, "Vec")?
+            .into_iter()
+            .map(T::from_resp_frame)
+            .collect()
+    }
+}
+
+impl<T: FromRespFrame + Eq + Hash, S: BuildHasher + Default> FromRespFrame for HashSet<T, S> {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        elements(frame, "HashSet")?
+            .into_iter()
+            .map(T::from_resp_frame)
+            .collect()
+    }
+}
+
+impl<T: FromRespFrame + Ord> FromRespFrame for BTreeSet<T> {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        elements(frame, "BTreeSet")?
+            .into_iter()
+            .map(T::from_resp_frame)
+            .collect()
+    }
+}
+
+impl<K, V, S> FromRespFrame for HashMap<K, V, S>
+where
+    K: FromRespFrame + Eq + Hash,
+    V: FromRespFrame,
+    S: BuildHasher + Default,
+{
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        pairs(frame, "HashMap")?
+            .into_iter()
+            .map(|(k, v)| Ok((K::from_resp_frame(k)?, V::from_resp_frame(v)?)))
+            .collect()
+    }
+}
+
+impl<K: FromRespFrame + Ord, V: FromRespFrame> FromRespFrame for BTreeMap<K, V> {
+    fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+        pairs(frame, "BTreeMap")?
+            .into_iter()
+            .map(|(k, v)| Ok((K::from_resp_frame(k)?, V::from_resp_frame(v)?)))
+            .collect()
+    }
+}
+
+macro_rules! impl_from_resp_frame_tuple {
+    ($len:literal; $($name:ident),+) => {
+        impl<$($name: FromRespFrame),+> FromRespFrame for ($($name,)+) {
+            fn from_resp_frame(frame: RespFrame) -> Result<Self, RespError> {
+                let mut elements = match reply(frame)? {
+                    RespFrame::Array(a) if a.len() == $len => a.0.into_iter(),
+                    frame => return Err(invalid(concat!("array of ", $len, " elements"), &frame)),
+                };
+                Ok(($(
+                    $name::from_resp_frame(elements.next().expect("length is checked"))?,
+                )+))
+            }
+        }
+    };
+}
+
+impl_from_resp_frame_tuple!(1; A);
+impl_from_resp_frame_tuple!(2; A, B);
+impl_from_resp_frame_tuple!(3; A, B, C);
+impl_from_resp_frame_tuple!(4; A, B, C, D);
+impl_from_resp_frame_tuple!(5; A, B, C, D, E);
+impl_from_resp_frame_tuple!(6; A, B, C, D, E, F);
+
+impl ToRespArgs for str {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        out.push(self.into());
+    }
+}
+
+impl ToRespArgs for String {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        self.as_str().write_resp_args(out);
+    }
+}
+
+impl ToRespArgs for char {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        self.encode_utf8(&mut [0; 4]).write_resp_args(out);
+    }
+}
+
+impl ToRespArgs for Bytes {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        out.push(BulkString::from_bytes(self.clone()));
+    }
+}
+
+impl ToRespArgs for BulkString {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        out.push(self.clone());
+    }
+}
+
+macro_rules! impl_to_resp_args_display {
+    ($($t:ty),*) => {
+        $(
+            impl ToRespArgs for $t {
+                fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+                    out.push(BulkString::new(self.to_string()));
+                }
+            }
+        )*
+    };
+}
+
+impl_to_resp_args_display!(i8, i16, i32, i64, i128, isize, u16, u32, u64, u128, usize, f32, f64);
+
+impl ToRespArgs for bool {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        out.push(if *self { "1" } else { "0" }.into());
+    }
+}
+
+impl<T: ToRespArgs + ?Sized> ToRespArgs for &T {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        (**self).write_resp_args(out);
+    }
+}
+
+impl<T: ToRespArgs> ToRespArgs for Option<T> {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        if let Some(value) = self {
+            value.write_resp_args(out);
+        }
+    }
+}
+
+impl<T: ToRespArgs> ToRespArgs for [T] {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        for value in self {
+            value.write_resp_args(out);
+        }
+    }
+}
+
+impl<T: ToRespArgs, const N: usize> ToRespArgs for [T; N] {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        self.as_slice().write_resp_args(out);
+    }
+}
+
+impl<T: ToRespArgs> ToRespArgs for Vec<T> {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        self.as_slice().write_resp_args(out);
+    }
+}
+
+impl<T: ToRespArgs, S> ToRespArgs for HashSet<T, S> {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        for value in self {
+            value.write_resp_args(out);
+        }
+    }
+}
+
+impl<T: ToRespArgs> ToRespArgs for BTreeSet<T> {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        for value in self {
+            value.write_resp_args(out);
+        }
+    }
+}
+
+impl<K: ToRespArgs, V: ToRespArgs, S> ToRespArgs for HashMap<K, V, S> {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        for (key, value) in self {
+            key.write_resp_args(out);
+            value.write_resp_args(out);
+        }
+    }
+}
+
+impl<K: ToRespArgs, V: ToRespArgs> ToRespArgs for BTreeMap<K, V> {
+    fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+        for (key, value) in self {
+            key.write_resp_args(out);
+            value.write_resp_args(out);
+        }
+    }
+}
+
+macro_rules! impl_to_resp_args_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: ToRespArgs),+> ToRespArgs for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn write_resp_args(&self, out: &mut Vec<BulkString>) {
+                let ($($name,)+) = self;
+                $($name.write_resp_args(out);)+
+            }
+        }
+    };
+}
+
+impl_to_resp_args_tuple!(A);
+impl_to_resp_args_tuple!(A, B);
+impl_to_resp_args_tuple!(A, B, C);
+impl_to_resp_args_tuple!(A, B, C, D);
+impl_to_resp_args_tuple!(A, B, C, D, E);
+impl_to_resp_args_tuple!(A, B, C, D, E, F);
+
+// strip attributes and turn error replies into errors
+fn reply(frame: RespFrame) -> Result<RespFrame, RespError> {
+    match frame {
+        RespFrame::Error(e) => Err(RespError::ErrorReply(e.0)),
+        RespFrame::BulkError(e) => Err(RespError::ErrorReply(e.0)),
+        RespFrame::Attributed(a) => reply(*a.frame),
+        frame => Ok(frame),
+    }
+}
+
+fn elements(frame: RespFrame, expected: &'static str) -> Result<Vec<RespFrame>, RespError> {
+    match reply(frame)? {
+        RespFrame::Array(a) => Ok(a.0),
+        RespFrame::Set(s) => Ok(s.0.into_iter().collect()),
+        RespFrame::Push(p) => Ok(p.0),
+        RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => Ok(vec![]),
+        frame => Err(invalid(expected, &frame)),
+    }
+}
+
+fn pairs(
+    frame: RespFrame,
+    expected: &'static str,
+) -> Result<Vec<(RespFrame, RespFrame)>, RespError> {
+    match reply(frame)? {
+        RespFrame::Map(m) => Ok(m.0.into_iter().collect()),
+        RespFrame::Array(a) if a.len() % 2 == 0 => Ok(flat_pairs(a.0).collect()),
+        RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => Ok(vec![]),
+        frame => Err(invalid(expected, &frame)),
+    }
+}
+
+/// Split an array of alternating keys and values into pairs, the caller checks that the
+/// length is even. RESP2 has no map type, replies like HGETALL are flat key-value arrays.
+pub(super) fn flat_pairs(frames: Vec<RespFrame>) -> impl Iterator<Item = (RespFrame, RespFrame)> {
+    let mut frames = frames.into_iter();
+    std::iter::from_fn(move || Some((frames.next()?, frames.next()?)))
+}
+
+/// The text of a string frame, numbers and booleans in replies often come as strings.
+pub(super) fn text(frame: &RespFrame) -> Option<&str> {
+    match frame {
+        RespFrame::SimpleString(s) => Some(s),
+        RespFrame::BulkString(s) => std::str::from_utf8(s).ok(),
+        _ => None,
+    }
+}
+
+fn invalid(expected: &'static str, frame: &RespFrame) -> RespError {
+    RespError::InvalidConversion {
+        expected,
+        found: describe(frame),
+    }
+}
+
+// a short description of the frame for error messages, long strings are cut
+fn describe(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::SimpleString(s) => format!("simple string {}", preview(s.as_bytes())),
+        RespFrame::Error(e) => format!("simple error {}", preview(e.as_bytes())),
+        RespFrame::BulkError(e) => format!("bulk error {}", preview(e.0.as_bytes())),
+        RespFrame::Integer(n) => format!("integer {}", n),
+        RespFrame::BulkString(s) => format!("bulk string {}", preview(s)),
+        RespFrame::NullBulkString(_) => "null bulk string".to_string(),
+        RespFrame::Array(a) => format!("array of {} elements", a.len()),
+        RespFrame::NullArray(_) => "null array".to_string(),
+        RespFrame::Null(_) => "null".to_string(),
+        RespFrame::Boolean(b) => format!("boolean {}", b),
+        RespFrame::Double(f) => format!("double {}", f),
+        RespFrame::BigNumber(n) => format!("big number {}", n.0),
+        RespFrame::VerbatimString(s) => format!("verbatim string {}", preview(s)),
+        RespFrame::Map(m) => format!("map of {} entries", m.len()),
+        RespFrame::Set(s) => format!("set of {} elements", s.len()),
+        RespFrame::Push(p) => format!("push of {} elements", p.len()),
+        RespFrame::Attributed(a) => describe(&a.frame),
+    }
+}
+
+fn preview(bytes: &[u8]) -> String {
+    const MAX_PREVIEW: usize = 32;
+    let s = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_PREVIEW)]);
+    if bytes.len() > MAX_PREVIEW {
+        format!("{:?}...", s)
+    } else {
+        format!("{:?}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Attribute, Attributed, BigNumber, Map, Null, NullBulkString, Set, SimpleError};
+
+    #[test]
+    fn test_from_resp_frame_scalars() -> Result<(), RespError> {
+        assert_eq!(i64::from_resp_frame(42.into())?, 42);
+        assert_eq!(u8::from_resp_frame(BulkString::from("7").into())?, 7);
+        assert_eq!(
+            u128::from_resp_frame(BigNumber::from(u128::MAX).into())?,
+            u128::MAX
+        );
+        assert_eq!(f64::from_resp_frame(1.5.into())?, 1.5);
+        assert_eq!(
+            f64::from_resp_frame(BulkString::from("-inf").into())?,
+            f64::NEG_INFINITY
+        );
+        assert!(bool::from_resp_frame(1.into())?);
+        assert!(!bool::from_resp_frame(BulkString::from("0").into())?);
+        assert_eq!(
+            String::from_resp_frame(BulkString::from("hi").into())?,
+            "hi"
+        );
+        assert_eq!(String::from_resp_frame(12.into())?, "12");
+        assert_eq!(
+            Bytes::from_resp_frame(BulkString::new(vec![0xff, 0]).into())?,
+            Bytes::from_static(&[0xff, 0])
+        );
+        assert_eq!(Option::<i64>::from_resp_frame(NullBulkString.into())?, None);
+
+        let attributed = Attributed::new(Attribute::new([]), 3);
+        assert_eq!(Option::<i64>::from_resp_frame(attributed.into())?, Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_resp_frame_collections() -> Result<(), RespError> {
+        let frame: RespFrame = Array::new(vec![
+            BulkString::from("a").into(),
+            1.into(),
+            BulkString::from("b").into(),
+            2.into(),
+        ])
+        .into();
+        assert_eq!(
+            Vec::<String>::from_resp_frame(frame.clone())?,
+            vec!["a", "1", "b", "2"]
+        );
+        let map = HashMap::<String, i64>::from_resp_frame(frame.clone())?;
+        assert_eq!(
+            map,
+            HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+        let tuple = <(String, u8, String, Option<u32>)>::from_resp_frame(frame)?;
+        assert_eq!(tuple, ("a".to_string(), 1, "b".to_string(), Some(2)));
+
+        let frame: RespFrame = Map::new([(1.into(), true.into())]).into();
+        assert_eq!(
+            BTreeMap::<u32, bool>::from_resp_frame(frame)?,
+            BTreeMap::from([(1, true)])
+        );
+        let frame: RespFrame = Set::new([1.into(), 2.into()]).into();
+        assert_eq!(
+            HashSet::<i64>::from_resp_frame(frame)?,
+            HashSet::from([1, 2])
+        );
+        assert_eq!(Vec::<i64>::from_resp_frame(Null.into())?, Vec::<i64>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_resp_frame_errors() {
+        assert_eq!(
+            u8::from_resp_frame(300.into()),
+            Err(RespError::InvalidConversion {
+                expected: "u8",
+                found: "integer 300".to_string()
+            })
+        );
+        assert_eq!(
+            i64::from_resp_frame(BulkString::from("x".repeat(40).as_str()).into())
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "Can't convert bulk string \"{}\"... into i64",
+                "x".repeat(32)
+            )
+        );
+        assert_eq!(
+            String::from_resp_frame(SimpleError::new("ERR wrong type").into()),
+            Err(RespError::ErrorReply("ERR wrong type".to_string()))
+        );
+        assert_eq!(
+            <(i64, i64)>::from_resp_frame(Array::new(vec![1.into()]).into()),
+            Err(RespError::InvalidConversion {
+                expected: "array of 2 elements",
+                found: "array of 1 elements".to_string()
+            })
+        );
+        assert!(Vec::<i64>::from_resp_frame(1.into()).is_err());
+    }
+
+    #[test]
+    fn test_to_resp_args() {
+        let args = ("SET", "key", 42, Some(1.5), None::<&str>, ["NX", "GET"]).to_resp_args();
+        let expected: Vec<BulkString> = ["SET", "key", "42", "1.5", "NX", "GET"]
+            .into_iter()
+            .map(BulkString::from)
+            .collect();
+        assert_eq!(args, expected);
+
+        let fields = BTreeMap::from([("a", true), ("b", false)]);
+        let command = ("HSET", "hash", &fields).to_command();
+        let expected: Vec<RespFrame> = ["HSET", "hash", "a", "1", "b", "0"]
+            .into_iter()
+            .map(|s| BulkString::from(s).into())
+            .collect();
+        assert_eq!(command, Array::new(expected));
+
+        let args = Bytes::from_static(b"\xff\x00").to_resp_args();
+        assert_eq!(args, vec![BulkString::new(vec![0xff, 0])]);
+    }
+}
@@ -0,0 +1,481 @@
+use bytes::BytesMut;
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, EnumAccess, IntoDeserializer, Unexpected, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+
+use super::{
+    convert::{flat_pairs, text},
+    RespDecode, RespError, RespFrame,
+};
+
+/// Deserialize a reply into any `DeserializeOwned` type.
+///
+/// Besides the direct mapping of `to_frame`, this accepts what Redis actually replies
+/// with: numbers and booleans may come as strings (`HGET` returns `"42"`), a struct or a
+/// map may come as a flat array of alternating keys and values (RESP2 `HGETALL`), all
+/// null types are `None`, and attributes are skipped. Error replies fail with
+/// `RespError::ErrorReply`.
+pub fn from_frame<T: DeserializeOwned>(frame: RespFrame) -> Result<T, RespError> {
+    T::deserialize(frame)
+}
+
+/// Decode exactly one frame from `buf` and deserialize it, see `from_frame`.
+pub fn from_bytes<T: DeserializeOwned>(buf: &[u8]) -> Result<T, RespError> {
+    let mut buf = BytesMut::from(buf);
+    let frame = RespFrame::decode(&mut buf)?;
+    if !buf.is_empty() {
+        return Err(RespError::Serde(format!(
+            "{} trailing bytes after the frame",
+            buf.len()
+        )));
+    }
+    from_frame(frame)
+}
+
+impl de::Error for RespError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        RespError::Serde(msg.to_string())
+    }
+}
+
+impl<'de> IntoDeserializer<'de, RespError> for RespFrame {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for RespFrame {
+    type Error = RespError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        match self {
+            RespFrame::SimpleString(s) => visitor.visit_string(s.0),
+            RespFrame::Error(e) => Err(RespError::ErrorReply(e.0)),
+            RespFrame::BulkError(e) => Err(RespError::ErrorReply(e.0)),
+            RespFrame::Integer(n) => visitor.visit_i64(n),
+            RespFrame::BulkString(s) => visit_bytes(&s, visitor),
+            RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => {
+                visitor.visit_unit()
+            }
+            RespFrame::Array(a) => visit_seq(a.0, visitor),
+            RespFrame::Boolean(b) => visitor.visit_bool(b),
+            RespFrame::Double(f) => visitor.visit_f64(f.0),
+            RespFrame::BigNumber(n) => {
+                if let Ok(n) = i128::try_from(&n) {
+                    visitor.visit_i128(n)
+                } else if let Ok(n) = n.parse::<u128>() {
+                    visitor.visit_u128(n)
+                } else {
+                    visitor.visit_string(n.0)
+                }
+            }
+            RespFrame::VerbatimString(s) => visit_bytes(&s, visitor),
+            RespFrame::Map(m) => visit_map(m.0, visitor),
+            RespFrame::Set(s) => visit_seq(s.0, visitor),
+            RespFrame::Push(p) => visit_seq(p.0, visitor),
+            RespFrame::Attributed(a) => a.frame.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        match self {
+            RespFrame::Attributed(a) => a.frame.deserialize_bool(visitor),
+            RespFrame::Integer(n @ (0 | 1)) => visitor.visit_bool(n == 1),
+            RespFrame::SimpleString(_) | RespFrame::BulkString(_) => match text(&self) {
+                Some("1" | "true") => visitor.visit_bool(true),
+                Some("0" | "false") => visitor.visit_bool(false),
+                _ => self.deserialize_any(visitor),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        if let RespFrame::Attributed(a) = self {
+            return a.frame.deserialize_i64(visitor);
+        }
+        let Some(s) = text(&self) else {
+            return self.deserialize_any(visitor);
+        };
+        if let Ok(n) = s.parse::<i64>() {
+            visitor.visit_i64(n)
+        } else if let Ok(n) = s.parse::<u64>() {
+            visitor.visit_u64(n)
+        } else if let Ok(n) = s.parse::<i128>() {
+            visitor.visit_i128(n)
+        } else if let Ok(n) = s.parse::<u128>() {
+            visitor.visit_u128(n)
+        } else {
+            Err(de::Error::invalid_type(Unexpected::Str(s), &visitor))
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        if let RespFrame::Attributed(a) = self {
+            return a.frame.deserialize_f64(visitor);
+        }
+        let Some(s) = text(&self) else {
+            return self.deserialize_any(visitor);
+        };
+        match s.parse::<f64>() {
+            Ok(f) => visitor.visit_f64(f),
+            Err(_) => Err(de::Error::invalid_type(Unexpected::Str(s), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        match self {
+            RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => {
+                visitor.visit_none()
+            }
+            RespFrame::Attributed(a) => a.frame.deserialize_option(visitor),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, RespError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        match self {
+            RespFrame::Array(a) if a.len() % 2 == 0 => visit_map(flat_pairs(a.0), visitor),
+            RespFrame::Attributed(a) => a.frame.deserialize_map(visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RespError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RespError> {
+        match self {
+            RespFrame::SimpleString(_) | RespFrame::BulkString(_) => visitor.visit_enum(Enum {
+                variant: self,
+                value: None,
+            }),
+            RespFrame::Map(m) if m.len() == 1 => {
+                let (variant, value) = m.0.into_iter().next().expect("map has one entry");
+                visitor.visit_enum(Enum {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            RespFrame::Attributed(a) => a.frame.deserialize_enum(name, variants, visitor),
+            _ => Err(de::Error::invalid_type(
+                unexpected(&self),
+                &"a variant name or a map with a single entry",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct identifier
+    }
+
+    // every integer and float type goes through the lenient i64 and f64 paths, serde's
+    // primitive visitors do the range checks
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RespError> {
+        self.deserialize_f64(visitor)
+    }
+}
+
+struct Enum {
+    variant: RespFrame,
+    value: Option<RespFrame>,
+}
+
+// the value of an externally tagged variant, none for a bare variant name
+struct Variant(Option<RespFrame>);
+
+impl<'de> EnumAccess<'de> for Enum {
+    type Error = RespError;
+    type Variant = Variant;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Variant), RespError> {
+        Ok((seed.deserialize(self.variant)?, Variant(self.value)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant {
+    type Error = RespError;
+
+    fn unit_variant(self) -> Result<(), RespError> {
+        match self.0 {
+            None => Ok(()),
+            Some(value) => de::Deserialize::deserialize(value),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, RespError> {
+        seed.deserialize(self.value()?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, RespError> {
+        self.value()?.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RespError> {
+        self.value()?.deserialize_map(visitor)
+    }
+}
+
+impl Variant {
+    fn value(self) -> Result<RespFrame, RespError> {
+        self.0.ok_or_else(|| {
+            de::Error::invalid_type(Unexpected::UnitVariant, &"a variant with a value")
+        })
+    }
+}
+
+fn visit_bytes<'de, V: Visitor<'de>>(bytes: &[u8], visitor: V) -> Result<V::Value, RespError> {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => visitor.visit_str(s),
+        Err(_) => visitor.visit_bytes(bytes),
+    }
+}
+
+fn visit_seq<'de, I, V>(frames: I, visitor: V) -> Result<V::Value, RespError>
+where
+    I: IntoIterator<Item = RespFrame>,
+    V: Visitor<'de>,
+{
+    let mut seq = SeqDeserializer::new(frames.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_map<'de, I, V>(pairs: I, visitor: V) -> Result<V::Value, RespError>
+where
+    I: IntoIterator<Item = (RespFrame, RespFrame)>,
+    V: Visitor<'de>,
+{
+    let mut map = MapDeserializer::new(pairs.into_iter());
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+fn unexpected(frame: &RespFrame) -> Unexpected<'_> {
+    match frame {
+        RespFrame::Integer(n) => Unexpected::Signed(*n),
+        RespFrame::Boolean(b) => Unexpected::Bool(*b),
+        RespFrame::Double(f) => Unexpected::Float(f.0),
+        RespFrame::Array(_) | RespFrame::Set(_) | RespFrame::Push(_) => Unexpected::Seq,
+        RespFrame::Map(_) => Unexpected::Map,
+        RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => {
+            Unexpected::Unit
+        }
+        _ => Unexpected::Other("frame"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        to_frame, Array, Attribute, Attributed, BulkString, Map, Null, NullBulkString, SimpleError,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        id: u64,
+        label: Option<String>,
+        shapes: Vec<Shape>,
+        ratio: f32,
+        active: bool,
+    }
+
+    #[test]
+    fn test_from_frame_roundtrip() -> Result<(), RespError> {
+        let item = Item {
+            id: 42,
+            label: Some("box".to_string()),
+            shapes: vec![Shape::Empty, Shape::Circle(0.5), Shape::Rect { w: 2, h: 3 }],
+            ratio: 1.5,
+            active: true,
+        };
+        let frame = to_frame(&item)?;
+        assert_eq!(from_frame::<Item>(frame)?, item);
+
+        let bytes = crate::to_bytes(&item)?;
+        assert_eq!(from_bytes::<Item>(&bytes)?, item);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_frame_hash_reply() -> Result<(), RespError> {
+        // what HGETALL returns over RESP2: every value is a bulk string
+        let frame: RespFrame = Array::new(vec![
+            BulkString::from("id").into(),
+            BulkString::from("7").into(),
+            BulkString::from("shapes").into(),
+            Array::new(vec![]).into(),
+            BulkString::from("ratio").into(),
+            BulkString::from("0.25").into(),
+            BulkString::from("active").into(),
+            BulkString::from("0").into(),
+            BulkString::from("label").into(),
+            NullBulkString.into(),
+        ])
+        .into();
+        let item: Item = from_frame(frame)?;
+        assert_eq!(
+            item,
+            Item {
+                id: 7,
+                label: None,
+                shapes: vec![],
+                ratio: 0.25,
+                active: false,
+            }
+        );
+
+        let frame: RespFrame = Array::new(vec![
+            BulkString::from("id").into(),
+            BulkString::from("7").into(),
+            BulkString::from("label").into(),
+            NullBulkString.into(),
+        ])
+        .into();
+        let map: HashMap<String, Option<String>> = from_frame(frame)?;
+        assert_eq!(map["id"], Some("7".to_string()));
+        assert_eq!(map["label"], None);
+
+        let map = Map::new([(BulkString::from("id").into(), 1.into())]);
+        let map: HashMap<String, i64> = from_frame(map.into())?;
+        assert_eq!(map["id"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_frame_skips_attributes() -> Result<(), RespError> {
+        let attributed = |frame: RespFrame| -> RespFrame {
+            let attributes = Attribute::new([(BulkString::from("ttl").into(), 3.into())]);
+            Attributed::new(attributes, frame).into()
+        };
+
+        let value: Option<String> = from_frame(attributed(Null.into()))?;
+        assert_eq!(value, None);
+        let value: Option<String> = from_frame(attributed(BulkString::from("a").into()))?;
+        assert_eq!(value, Some("a".to_string()));
+        let value: bool = from_frame(attributed(BulkString::from("true").into()))?;
+        assert!(value);
+        let value: u32 = from_frame(attributed(BulkString::from("42").into()))?;
+        assert_eq!(value, 42);
+        let value: f64 = from_frame(attributed(BulkString::from("0.5").into()))?;
+        assert_eq!(value, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_frame_errors() {
+        let ret = from_frame::<String>(SimpleError::new("ERR no such key").into());
+        assert_eq!(
+            ret,
+            Err(RespError::ErrorReply("ERR no such key".to_string()))
+        );
+
+        let ret = from_frame::<u8>(BulkString::from("300").into());
+        assert!(matches!(ret, Err(RespError::Serde(_))));
+
+        let ret = from_frame::<Shape>(1.into());
+        assert!(matches!(ret, Err(RespError::Serde(_))));
+
+        let ret = from_bytes::<i64>(b":+1\r\n:+2\r\n");
+        assert_eq!(
+            ret,
+            Err(RespError::Serde(
+                "5 trailing bytes after the frame".to_string()
+            ))
+        );
+    }
+}
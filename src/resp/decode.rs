@@ -1,133 +1,184 @@
-use std::collections::BTreeMap;
-
 use super::{
-    Array, BulkError, BulkString, Map, Null, NullArray, NullBulkString, RespDecode, RespError,
-    RespFrame, Set, SimpleError, SimpleString,
+    Array, Attribute, Attributed, BigNumber, BulkError, BulkString, DecodeLimits, Map, Null,
+    NullArray, NullBulkString, Push, RespDecode, RespError, RespFrame, RespParser, Set,
+    SimpleError, SimpleString, VerbatimString,
 };
 use anyhow::Result;
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 
 const CRLF: &[u8] = b"\r\n";
 const CRLF_LEN: usize = CRLF.len();
+// bulk strings at least this long share the read buffer, shorter ones are copied so a
+// small value doesn't keep the whole read allocation alive while it is stored
+const SHARED_BULK_MIN_LEN: usize = 4096;
 
 impl RespDecode for RespFrame {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let mut iter = buf.iter().peekable();
-        match iter.peek() {
-            Some(b'+') => {
-                let frame = SimpleString::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'-') => {
-                let frame = SimpleError::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'*') => {
-                // try null array first
-                match NullArray::decode(buf) {
-                    Ok(frame) => Ok(frame.into()),
-                    Err(RespError::NotComplete) => Err(RespError::NotComplete),
-                    Err(_) => {
-                        let frame = Array::decode(buf)?;
-                        Ok(frame.into())
-                    }
-                }
-            }
-            Some(b':') => {
-                let frame = i64::decode(buf)?;
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_frame(buf)
+    }
+}
+
+// decode a frame from a buffer which is known to hold at least one complete frame,
+// so nested aggregates are not scanned again
+pub(super) fn parse_frame(buf: &mut BytesMut) -> Result<RespFrame, RespError> {
+    let mut iter = buf.iter().peekable();
+    match iter.peek() {
+        // utf8 was already checked by the scanner if the parser is strict
+        Some(b'+') => {
+            let frame = parse_simple_string(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'-') => {
+            let frame = parse_simple_error(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'*') => {
+            if buf.starts_with(b"*-1\r\n") {
+                let frame = NullArray::decode(buf)?;
                 Ok(frame.into())
-            }
-            Some(b'#') => {
-                let frame = bool::decode(buf)?;
+            } else {
+                let frame = parse_array(buf)?;
                 Ok(frame.into())
             }
-            Some(b'$') => {
-                // try null bulk string first
-                match NullBulkString::decode(buf) {
-                    Ok(frame) => Ok(frame.into()),
-                    Err(RespError::NotComplete) => Err(RespError::NotComplete),
-                    Err(_) => {
-                        let frame = BulkString::decode(buf)?;
-                        Ok(frame.into())
-                    }
-                }
-            }
-            Some(b'~') => {
-                let frame = Set::decode(buf)?;
+        }
+        Some(b':') => {
+            let frame = i64::decode(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'#') => {
+            let frame = bool::decode(buf)?;
+            Ok(frame.into())
+        }
+        Some(b',') => {
+            let frame = f64::decode(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'(') => {
+            let frame = BigNumber::decode(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'=') => {
+            let frame = parse_verbatim(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'_') => {
+            let frame = Null::decode(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'$') => {
+            if buf.starts_with(b"$-1\r\n") {
+                let frame = NullBulkString::decode(buf)?;
                 Ok(frame.into())
-            }
-            Some(b'%') => {
-                let frame = Map::decode(buf)?;
+            } else if buf.starts_with(b"$?\r\n") {
+                let frame = parse_streamed_bulk_string(buf)?;
                 Ok(frame.into())
-            }
-            Some(b'!') => {
-                let frame = BulkError::decode(buf)?;
+            } else {
+                let frame = parse_bulk_string(buf)?;
                 Ok(frame.into())
             }
-            _ => Err(RespError::InvalidFrame("invalid frame type".into())),
         }
+        Some(b'~') => {
+            let frame = parse_set(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'%') => {
+            let frame = parse_map(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'>') => {
+            let frame = parse_push(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'|') => {
+            let frame = parse_attributed(buf)?;
+            Ok(frame.into())
+        }
+        Some(b'!') => {
+            let frame = parse_bulk_error(buf)?;
+            Ok(frame.into())
+        }
+        None => Err(RespError::NotComplete),
+        _ => Err(RespError::InvalidFrame("invalid frame type".into())),
     }
 }
 
 impl RespDecode for SimpleString {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let prefix = "+";
-        let end = extract_simple_frame_data(buf, prefix)?;
-
-        // split the buffer
-        let data = buf.split_to(end + CRLF_LEN);
-        let s = String::from_utf8_lossy(&data[1..end]);
-
-        Ok(SimpleString::new(s.to_string()))
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        // the default parser rejects invalid utf8
+        frame_length(buf, limits)?;
+        parse_simple_string(buf)
     }
 }
 
 impl RespDecode for SimpleError {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let prefix = "-";
-        let end = extract_simple_frame_data(buf, prefix)?;
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_simple_error(buf)
+    }
+}
 
-        let data = buf.split_to(end + CRLF_LEN);
-        let s = String::from_utf8_lossy(&data[1..end]);
+fn parse_simple_string(buf: &mut BytesMut) -> Result<SimpleString, RespError> {
+    let prefix = "+";
+    let end = extract_simple_frame_data(buf, prefix)?;
 
-        Ok(SimpleError::new(s.to_string()))
-    }
+    // split the buffer
+    let data = buf.split_to(end + CRLF_LEN);
+    let s = String::from_utf8_lossy(&data[1..end]);
+
+    SimpleString::try_new(s)
 }
 
-impl RespDecode for BulkError {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let prefix = "!";
-        let end = extract_simple_frame_data(buf, prefix)?;
+fn parse_simple_error(buf: &mut BytesMut) -> Result<SimpleError, RespError> {
+    let prefix = "-";
+    let end = extract_simple_frame_data(buf, prefix)?;
 
-        let data = buf.split_to(end + CRLF_LEN);
-        let s = String::from_utf8_lossy(&data[prefix.len()..end]);
-        Ok(BulkError::new(s.to_string()))
+    let data = buf.split_to(end + CRLF_LEN);
+    let s = String::from_utf8_lossy(&data[1..end]);
+
+    SimpleError::try_new(s)
+}
+
+impl RespDecode for BulkError {
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_bulk_error(buf)
     }
 }
 
+fn parse_bulk_error(buf: &mut BytesMut) -> Result<BulkError, RespError> {
+    let (end, len) = parse_length(buf, "!")?;
+    let remained = payload(buf, end, len)?;
+    // replacing invalid bytes would change the length and break the round trip
+    let s = String::from_utf8(remained[..len].to_vec())?;
+
+    buf.advance(end + CRLF_LEN + len + CRLF_LEN);
+    Ok(BulkError::new(s))
+}
+
 impl RespDecode for Null {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+    fn decode_with(buf: &mut BytesMut, _limits: &DecodeLimits) -> Result<Self, RespError> {
         extract_fixed_data(buf, "_\r\n", "Null")?;
         Ok(Null)
     }
 }
 
 impl RespDecode for NullArray {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+    fn decode_with(buf: &mut BytesMut, _limits: &DecodeLimits) -> Result<Self, RespError> {
         extract_fixed_data(buf, "*-1\r\n", "NullArray")?;
         Ok(NullArray)
     }
 }
 
 impl RespDecode for NullBulkString {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+    fn decode_with(buf: &mut BytesMut, _limits: &DecodeLimits) -> Result<Self, RespError> {
         extract_fixed_data(buf, "$-1\r\n", "NullBulkString")?;
         Ok(NullBulkString)
     }
 }
 
 impl RespDecode for i64 {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+    fn decode_with(buf: &mut BytesMut, _limits: &DecodeLimits) -> Result<Self, RespError> {
         let prefix = ":";
         let end = extract_simple_frame_data(buf, prefix)?;
         let data = buf.split_to(end + CRLF_LEN);
@@ -137,110 +188,235 @@ impl RespDecode for i64 {
     }
 }
 
+impl RespDecode for BigNumber {
+    fn decode_with(buf: &mut BytesMut, _limits: &DecodeLimits) -> Result<Self, RespError> {
+        let prefix = "(";
+        let end = extract_simple_frame_data(buf, prefix)?;
+        let data = buf.split_to(end + CRLF_LEN);
+        let s = String::from_utf8_lossy(&data[prefix.len()..end]);
+
+        BigNumber::new(s)
+    }
+}
+
 impl RespDecode for bool {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        match extract_fixed_data(buf, "#t\r\n", "Bool") {
-            Ok(_) => Ok(true),
-            Err(RespError::NotComplete) => Err(RespError::NotComplete),
-            Err(_) => match extract_fixed_data(buf, "#f\r\n", "Bool") {
-                Ok(_) => Ok(false),
-                Err(e) => Err(e),
-            },
-        }
+    fn decode_with(buf: &mut BytesMut, _limits: &DecodeLimits) -> Result<Self, RespError> {
+        let end = extract_simple_frame_data(buf, "#")?;
+        // the line is complete, more data can't turn anything else into a boolean
+        let b = match &buf[1..end] {
+            b"t" => true,
+            b"f" => false,
+            s => {
+                return Err(RespError::InvalidFrame(format!(
+                    "invalid boolean {:?}",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        buf.advance(end + CRLF_LEN);
+        Ok(b)
     }
 }
 
 impl RespDecode for BulkString {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let (end, len) = parse_length(buf, "$")?;
-        let remained = &buf[end + CRLF_LEN..];
-        if remained.len() < len + CRLF_LEN {
-            return Err(RespError::NotComplete);
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        if buf.starts_with(b"$?\r\n") {
+            return parse_streamed_bulk_string(buf);
         }
+        parse_bulk_string(buf)
+    }
+}
 
-        buf.advance(end + CRLF_LEN);
+fn parse_bulk_string(buf: &mut BytesMut) -> Result<BulkString, RespError> {
+    let (end, len) = parse_length(buf, "$")?;
+    payload(buf, end, len)?;
+
+    buf.advance(end + CRLF_LEN);
+
+    let data = if len >= SHARED_BULK_MIN_LEN {
+        buf.split_to(len).freeze()
+    } else {
+        let data = Bytes::copy_from_slice(&buf[..len]);
+        buf.advance(len);
+        data
+    };
+    buf.advance(CRLF_LEN);
+    Ok(BulkString::from_bytes(data))
+}
 
-        let data = buf.split_to(len + CRLF_LEN);
-        Ok(BulkString::new(data[..len].to_vec()))
+impl RespDecode for VerbatimString {
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_verbatim(buf)
     }
 }
 
-impl RespDecode for Array {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let prefix = "*";
-        let (end, len) = parse_length(buf, prefix)?;
-        let total_len = cal_total_length(buf, len, prefix)?;
+fn parse_verbatim(buf: &mut BytesMut) -> Result<VerbatimString, RespError> {
+    let (end, len) = parse_length(buf, "=")?;
+    let remained = payload(buf, end, len)?;
+    if len < 4 || remained[3] != b':' {
+        return Err(RespError::InvalidFrame(
+            "verbatim string must start with <encoding>:".to_string(),
+        ));
+    }
 
-        if buf.len() < total_len {
-            return Err(RespError::NotComplete);
-        }
+    buf.advance(end + CRLF_LEN);
 
-        buf.advance(end + CRLF_LEN);
+    let data = buf.split_to(len + CRLF_LEN);
+    let format = [data[0], data[1], data[2]];
+    Ok(VerbatimString::new(format, data[4..len].to_vec()))
+}
 
-        let mut array = Vec::with_capacity(len);
-        for _ in 0..len {
-            let frame = RespFrame::decode(buf)?;
-            array.push(frame);
-        }
-        Ok(Array::new(array))
+impl RespDecode for Array {
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_array(buf)
     }
 }
 
 impl RespDecode for f64 {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+    fn decode_with(buf: &mut BytesMut, _limits: &DecodeLimits) -> Result<Self, RespError> {
         let prefix = ",";
         let end = extract_simple_frame_data(buf, prefix)?;
         let data = buf.split_to(end + CRLF_LEN);
         let s = String::from_utf8_lossy(&data[prefix.len()..end]);
-        Ok(s.parse()?)
+        match s.as_ref() {
+            "inf" | "+inf" => Ok(f64::INFINITY),
+            "-inf" => Ok(f64::NEG_INFINITY),
+            "nan" | "-nan" => Ok(f64::NAN),
+            // rust also accepts words like "infinity" which are not valid RESP
+            s if s
+                .bytes()
+                .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b)) =>
+            {
+                Ok(s.parse()?)
+            }
+            s => Err(RespError::InvalidFrame(format!("invalid double {:?}", s))),
+        }
     }
 }
 
 impl RespDecode for Map {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let prefix = "*";
-        let (end, len) = parse_length(buf, prefix)?;
-        let total_len = cal_total_length(buf, len, prefix)?;
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_map(buf)
+    }
+}
 
-        if buf.len() < total_len {
-            return Err(RespError::NotComplete);
-        }
+impl RespDecode for Set {
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_set(buf)
+    }
+}
 
-        buf.advance(end + CRLF_LEN);
+impl RespDecode for Push {
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_push(buf)
+    }
+}
 
-        let mut frames = Map::new(BTreeMap::new());
-        for _ in 0..len {
-            let key = SimpleString::decode(buf)?;
-            let value = RespFrame::decode(buf)?;
-            frames.insert(key.0, value);
-        }
+impl RespDecode for Attributed {
+    fn decode_with(buf: &mut BytesMut, limits: &DecodeLimits) -> Result<Self, RespError> {
+        frame_length(buf, limits)?;
+        parse_attributed(buf)
+    }
+}
+
+fn parse_array(buf: &mut BytesMut) -> Result<Array, RespError> {
+    let frames = parse_aggregate(buf, "*", parse_frame)?;
+    Ok(Array::new(frames))
+}
 
-        Ok(frames)
+fn parse_map(buf: &mut BytesMut) -> Result<Map, RespError> {
+    let pairs = parse_aggregate(buf, "%", |buf| {
+        let key = parse_frame(buf)?;
+        let value = parse_frame(buf)?;
+        Ok((key, value))
+    })?;
+    Ok(Map::new(pairs))
+}
+
+fn parse_set(buf: &mut BytesMut) -> Result<Set, RespError> {
+    let frames = parse_aggregate(buf, "~", parse_frame)?;
+    Ok(Set::new(frames))
+}
+
+fn parse_push(buf: &mut BytesMut) -> Result<Push, RespError> {
+    let frames = parse_aggregate(buf, ">", parse_frame)?;
+    Ok(Push::new(frames))
+}
+
+fn parse_attributed(buf: &mut BytesMut) -> Result<Attributed, RespError> {
+    let (end, len) = parse_length(buf, "|")?;
+    buf.advance(end + CRLF_LEN);
+
+    let mut pairs = Vec::with_capacity(len);
+    for _ in 0..len {
+        let key = parse_frame(buf)?;
+        let value = parse_frame(buf)?;
+        pairs.push((key, value));
     }
+    let frame = parse_frame(buf)?;
+    Ok(Attributed::new(Attribute::new(pairs), frame))
 }
 
-impl RespDecode for Set {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let prefix = "~";
-        let (end, len) = parse_length(buf, prefix)?;
-        let total_len = cal_total_length(buf, len, prefix)?;
+// parse the items of a sized aggregate, or of a streamed one ("*?\r\n" ... ".\r\n")
+fn parse_aggregate<T>(
+    buf: &mut BytesMut,
+    prefix: &str,
+    mut parse: impl FnMut(&mut BytesMut) -> Result<T, RespError>,
+) -> Result<Vec<T>, RespError> {
+    let streamed = format!("{}?\r\n", prefix);
+    if buf.starts_with(streamed.as_bytes()) {
+        buf.advance(streamed.len());
 
-        if buf.len() < total_len {
-            return Err(RespError::NotComplete);
+        let mut items = Vec::new();
+        while !buf.starts_with(b".\r\n") {
+            items.push(parse(buf)?);
         }
+        buf.advance(3);
+        return Ok(items);
+    }
 
-        buf.advance(end + CRLF_LEN);
+    let (end, len) = parse_length(buf, prefix)?;
+    buf.advance(end + CRLF_LEN);
 
-        let mut frames = Set::new(Vec::new());
-        for _ in 0..len {
-            let frame = RespFrame::decode(buf)?;
-            frames.push(frame);
-        }
+    let mut items = Vec::with_capacity(len);
+    for _ in 0..len {
+        items.push(parse(buf)?);
+    }
+    Ok(items)
+}
 
-        Ok(frames)
+// - streamed string: "$?\r\n;<length>\r\n<data>\r\n...;0\r\n"
+fn parse_streamed_bulk_string(buf: &mut BytesMut) -> Result<BulkString, RespError> {
+    buf.advance(b"$?\r\n".len());
+
+    let mut data = Vec::new();
+    loop {
+        let (end, len) = parse_length(buf, ";")?;
+        buf.advance(end + CRLF_LEN);
+        if len == 0 {
+            return Ok(BulkString::new(data));
+        }
+        if buf.len() < len + CRLF_LEN {
+            return Err(RespError::NotComplete);
+        }
+        data.extend_from_slice(&buf[..len]);
+        buf.advance(len + CRLF_LEN);
     }
 }
 
+// one pass over the buffer to make sure a whole frame is available before consuming it
+fn frame_length(buf: &[u8], limits: &DecodeLimits) -> Result<usize, RespError> {
+    RespParser::with_limits(*limits)
+        .scan(buf)?
+        .ok_or(RespError::NotComplete)
+}
+
 #[allow(dead_code)]
 fn extract_fixed_data(
     buf: &mut BytesMut,
@@ -278,18 +454,23 @@ fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<usize, RespErro
     Ok(end)
 }
 
-fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
-    let mut count = 0;
-    for i in 1..buf.len() - 1 {
-        if buf[i] == b'\r' && buf[i + 1] == b'\n' {
-            count += 1;
-            if count == nth {
-                return Some(i);
-            }
-        }
+// the data after a length header and its CRLF, holding at least `len` bytes and a CRLF
+fn payload(buf: &[u8], end: usize, len: usize) -> Result<&[u8], RespError> {
+    let remained = buf.get(end + CRLF_LEN..).ok_or(RespError::NotComplete)?;
+    if remained.len() < len.saturating_add(CRLF_LEN) {
+        return Err(RespError::NotComplete);
     }
+    Ok(remained)
+}
 
-    None
+fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
+    // the type byte is never part of a CRLF
+    buf.windows(CRLF_LEN)
+        .enumerate()
+        .skip(1)
+        .filter(|(_, w)| *w == CRLF)
+        .nth(nth.checked_sub(1)?)
+        .map(|(i, _)| i)
 }
 
 fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
@@ -298,22 +479,183 @@ fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
     Ok((end, s.parse()?))
 }
 
-fn cal_total_length(buf: &[u8], len: usize, prefix: &str) -> Result<usize, RespError> {
-    let data = &buf[len + CRLF_LEN..];
-    match prefix {
-        "*" | "~" => find_crlf(data, len)
-            .map(|end| len + CRLF_LEN + end)
-            .ok_or(RespError::NotComplete),
-        "%" => find_crlf(data, len * 2)
-            .map(|end| len + CRLF_LEN + end)
-            .ok_or(RespError::NotComplete),
-        _ => Ok(len + CRLF_LEN),
-    }
-}
-
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
+    use proptest::prelude::*;
+
     use super::*;
+    use crate::RespEncode;
+
+    fn arb_frame() -> impl Strategy<Value = RespFrame> {
+        let leaf = prop_oneof![
+            "[a-zA-Z0-9 ]{0,16}".prop_map(|s| SimpleString::new(s).into()),
+            "[a-zA-Z0-9 ]{0,16}".prop_map(|s| SimpleError::new(s).into()),
+            any::<String>().prop_map(|s| BulkError::new(s).into()),
+            any::<i64>().prop_map(RespFrame::from),
+            prop::collection::vec(any::<u8>(), 0..32).prop_map(|v| BulkString::new(v).into()),
+            Just(NullBulkString.into()),
+            Just(NullArray.into()),
+            Just(Null.into()),
+            any::<bool>().prop_map(RespFrame::from),
+            (prop::num::f64::POSITIVE
+                | prop::num::f64::NEGATIVE
+                | prop::num::f64::NORMAL
+                | prop::num::f64::SUBNORMAL
+                | prop::num::f64::ZERO
+                | prop::num::f64::INFINITE
+                | prop::num::f64::QUIET_NAN)
+                .prop_map(RespFrame::from),
+            "[+-]?[0-9]{1,48}".prop_map(|s| BigNumber::new(s).unwrap().into()),
+            ("[a-z]{3}", prop::collection::vec(any::<u8>(), 0..32)).prop_map(|(f, v)| {
+                let format = f.as_bytes().try_into().unwrap();
+                VerbatimString::new(format, v).into()
+            }),
+        ];
+        leaf.prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(|v| Array::new(v).into()),
+                prop::collection::vec(inner.clone(), 0..8).prop_map(|v| Set::new(v).into()),
+                prop::collection::vec(inner.clone(), 0..8).prop_map(|v| Push::new(v).into()),
+                prop::collection::vec((inner.clone(), inner.clone()), 0..8)
+                    .prop_map(|v| Map::new(v).into()),
+                (
+                    prop::collection::vec((inner.clone(), inner.clone()), 0..4),
+                    inner
+                )
+                    .prop_map(|(v, f)| Attributed::new(Attribute::new(v), f).into()),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_f64_roundtrip(n in any::<f64>()) {
+            let mut buf = BytesMut::from(&n.encode()[..]);
+            let decoded = f64::decode(&mut buf);
+            if n.is_nan() {
+                prop_assert!(decoded?.is_nan());
+            } else {
+                prop_assert_eq!(decoded?.to_bits(), n.to_bits());
+            }
+        }
+
+        #[test]
+        fn test_streamed_roundtrip(
+            frames in prop::collection::vec(arb_frame(), 0..8),
+            data in prop::collection::vec(any::<u8>(), 0..64),
+            chunk_size in 1usize..16,
+        ) {
+            let mut buf = BytesMut::new();
+            buf.extend_from_slice(&Array::new(frames.clone()).encode_streamed());
+            buf.extend_from_slice(&BulkString::new(data.clone()).encode_chunked(chunk_size));
+
+            prop_assert_eq!(Array::decode(&mut buf), Ok(Array::new(frames)));
+            prop_assert_eq!(RespFrame::decode(&mut buf), Ok(BulkString::new(data).into()));
+            prop_assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn test_frame_roundtrip(frame in arb_frame()) {
+            let mut buf = BytesMut::from(&frame.clone().encode()[..]);
+            let decoded = RespFrame::decode(&mut buf);
+            prop_assert_eq!(decoded, Ok(frame));
+            prop_assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn test_frame_roundtrip_partial(frame in arb_frame(), cut in any::<prop::sample::Index>()) {
+            let encoded = frame.clone().encode();
+            let cut = cut.index(encoded.len());
+            let mut buf = BytesMut::from(&encoded[..cut]);
+            prop_assert_eq!(RespFrame::decode(&mut buf), Err(RespError::NotComplete));
+            prop_assert_eq!(buf.len(), cut);
+
+            buf.extend_from_slice(&encoded[cut..]);
+            prop_assert_eq!(RespFrame::decode(&mut buf), Ok(frame));
+            prop_assert!(buf.is_empty());
+        }
+
+        // the decoders only ever return errors on garbage, they never panic
+        #[test]
+        fn test_decode_arbitrary_bytes(data in prop::collection::vec(any::<u8>(), 0..256)) {
+            decode_all(&data);
+        }
+
+        #[test]
+        fn test_decode_mutated_frame(
+            frame in arb_frame(),
+            at in any::<prop::sample::Index>(),
+            byte in prop::sample::select(b"*$:+-!=%~>|_#,(;.?\r\n-019".to_vec()),
+            truncate in any::<bool>(),
+        ) {
+            let mut data = frame.encode();
+            let at = at.index(data.len());
+            if truncate {
+                data.truncate(at);
+            } else {
+                data[at] = byte;
+            }
+            decode_all(&data);
+        }
+    }
+
+    fn decode_all(data: &[u8]) {
+        let ret = RespFrame::decode(&mut BytesMut::from(data));
+        // once the scanner has delimited a frame, decoding it must not wait for more data
+        if let Ok(Some(_)) = RespParser::new().scan(data) {
+            assert_ne!(ret, Err(RespError::NotComplete), "{:?}", data);
+        }
+        let _ = bool::decode(&mut BytesMut::from(data));
+        let _ = SimpleString::decode(&mut BytesMut::from(data));
+        let _ = BulkError::decode(&mut BytesMut::from(data));
+        let _ = BulkString::decode(&mut BytesMut::from(data));
+        let _ = VerbatimString::decode(&mut BytesMut::from(data));
+        let _ = Array::decode(&mut BytesMut::from(data));
+        let _ = Map::decode(&mut BytesMut::from(data));
+        let _ = BigNumber::decode(&mut BytesMut::from(data));
+        let _ = f64::decode(&mut BytesMut::from(data));
+
+        let mut buf = BytesMut::from(data);
+        let mut parser = RespParser::new();
+        while let Ok(Some(_)) = parser.parse_request(&mut buf) {}
+    }
+
+    #[test]
+    fn test_decode_complete_frames() {
+        for data in [
+            &b"#\r\n"[..],
+            b"#x\r\n",
+            b"#tt\r\n",
+            b"*1\r\n#\r\n",
+            b"_x\r\n",
+        ] {
+            decode_all(data);
+        }
+    }
+
+    #[test]
+    fn test_bool_decode() -> Result<()> {
+        let mut buf = BytesMut::from(&b"#t\r\n#f\r\n#t"[..]);
+        assert!(bool::decode(&mut buf)?);
+        assert!(!bool::decode(&mut buf)?);
+        assert_eq!(bool::decode(&mut buf), Err(RespError::NotComplete));
+
+        let mut buf = BytesMut::from(&b"#\r\n"[..]);
+        assert!(matches!(
+            RespFrame::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_crlf_short_input() {
+        assert_eq!(find_crlf(b"", 1), None);
+        assert_eq!(find_crlf(b"\r\n", 0), None);
+        assert_eq!(find_crlf(b"+\r\n", 1), Some(1));
+    }
 
     #[test]
     fn test_simple_string_decode() -> Result<()> {
@@ -331,9 +673,29 @@ mod tests {
         let frame = SimpleString::decode(&mut buf)?;
         assert_eq!(frame, SimpleString::new("hello".to_string()));
 
+        // a bare LF would make the string impossible to encode again
+        buf.extend_from_slice(b"+a\nb\r\n");
+        let ret = SimpleString::decode(&mut buf);
+        assert_eq!(ret, Err(RespError::InvalidSimpleLine("a\nb".to_string())));
+
         Ok(())
     }
 
+    #[test]
+    fn test_simple_string_decode_invalid_utf8() {
+        let mut buf = BytesMut::from(&b"+hello \xff\r\n"[..]);
+        assert!(matches!(
+            SimpleString::decode(&mut buf),
+            Err(RespError::InvalidUtf8(_))
+        ));
+
+        let mut buf = BytesMut::from(&b"-ERR \xc3\r\n"[..]);
+        assert!(matches!(
+            SimpleError::decode(&mut buf),
+            Err(RespError::InvalidUtf8(_))
+        ));
+    }
+
     #[test]
     fn test_simple_error_decode() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -351,6 +713,69 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bulk_error_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"!21\r\nSYNTAX invalid syntax\r\n");
+
+        let frame = BulkError::decode(&mut buf)?;
+        assert_eq!(frame, BulkError::new("SYNTAX invalid syntax"));
+        assert!(buf.is_empty());
+
+        // the message may contain CRLF as it is length prefixed
+        buf.extend_from_slice(b"!11\r\nERR a\r\nb\r\nc\r");
+        let ret = RespFrame::decode(&mut buf);
+        assert_eq!(ret, Err(RespError::NotComplete));
+        buf.extend_from_slice(b"\n+OK\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame, BulkError::new("ERR a\r\nb\r\nc").into());
+        assert_eq!(&buf[..], b"+OK\r\n");
+
+        let frame = BulkError::new("ERR \r\n\u{1f600}");
+        let mut buf = BytesMut::from(&frame.clone().encode()[..]);
+        assert_eq!(BulkError::decode(&mut buf)?, frame);
+
+        // invalid utf8 is rejected and the frame is left in the buffer
+        let mut buf = BytesMut::from(&b"!3\r\n\xff\xfe\xfd\r\n"[..]);
+        let ret = BulkError::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::Utf8Error(_))));
+        assert_eq!(buf.len(), 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"$999999999999\r\n");
+        let ret = BulkString::decode(&mut buf);
+        assert_eq!(ret, Err(RespError::BulkLengthExceeded(999999999999)));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*999999999999\r\n");
+        let ret = RespFrame::decode(&mut buf);
+        assert_eq!(ret, Err(RespError::AggregateLengthExceeded(999999999999)));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(&b"*1\r\n".repeat(200));
+        let ret = Array::decode(&mut buf);
+        assert_eq!(ret, Err(RespError::NestingDepthExceeded(128)));
+
+        let limits = DecodeLimits {
+            max_bulk_len: 4,
+            max_depth: 1,
+            ..Default::default()
+        };
+        let mut buf = BytesMut::from(&b"$5\r\nhello\r\n"[..]);
+        let ret = BulkString::decode_with(&mut buf, &limits);
+        assert_eq!(ret, Err(RespError::BulkLengthExceeded(5)));
+        let mut buf = BytesMut::from(&b"*1\r\n*1\r\n:1\r\n"[..]);
+        let ret = RespFrame::decode_with(&mut buf, &limits);
+        assert_eq!(ret, Err(RespError::NestingDepthExceeded(1)));
+        let mut buf = BytesMut::from(&b"*1\r\n$4\r\nPING\r\n"[..]);
+        let ret = Array::decode_with(&mut buf, &limits);
+        assert_eq!(ret, Ok(Array::new(vec![BulkString::new("PING").into()])));
+    }
+
     #[test]
     fn test_f64_decode() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -362,6 +787,100 @@ mod tests {
         buf.extend_from_slice(b",3.14768");
         let ret = f64::decode(&mut buf);
         assert_eq!(ret, Err(RespError::NotComplete));
+        buf.clear();
+
+        buf.extend_from_slice(b",inf\r\n,-inf\r\n,nan\r\n,-0\r\n,1.5E-3\r\n,10\r\n");
+        assert_eq!(f64::decode(&mut buf)?, f64::INFINITY);
+        assert_eq!(f64::decode(&mut buf)?, f64::NEG_INFINITY);
+        assert!(f64::decode(&mut buf)?.is_nan());
+        let frame = f64::decode(&mut buf)?;
+        assert!(frame == 0.0 && frame.is_sign_negative());
+        assert_eq!(f64::decode(&mut buf)?, 1.5e-3);
+        assert_eq!(f64::decode(&mut buf)?, 10.0);
+
+        for s in [&b",infinity\r\n"[..], b",NaN\r\n", b",0x10\r\n"] {
+            let mut buf = BytesMut::from(s);
+            assert!(matches!(
+                f64::decode(&mut buf),
+                Err(RespError::InvalidFrame(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_big_number_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"(3492890328409238509324850943850943825024385\r\n");
+
+        let frame = BigNumber::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            BigNumber::new("3492890328409238509324850943850943825024385")?
+        );
+
+        buf.extend_from_slice(b"(-170141183460469231731687303715884105728\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        let RespFrame::BigNumber(n) = frame else {
+            panic!("expect BigNumber, got {:?}", frame);
+        };
+        assert_eq!(i128::try_from(&n)?, i128::MIN);
+
+        buf.extend_from_slice(b"(12a\r\n");
+        let ret = BigNumber::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::InvalidFrame(_))));
+
+        // equal values decode to the same number whatever their spelling
+        buf.extend_from_slice(b"~4\r\n(5\r\n(+5\r\n(005\r\n(-0\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        let expected = Set::new([BigNumber::from(5i64).into(), BigNumber::from(0i64).into()]);
+        assert_eq!(frame, expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verbatim_string_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"=15\r\ntxt:Some string\r\n");
+
+        let frame = VerbatimString::decode(&mut buf)?;
+        assert_eq!(frame, VerbatimString::new(*b"txt", "Some string"));
+        assert_eq!(frame.format(), b"txt");
+
+        buf.extend_from_slice(b"=10\r\nmkd:# a\r\nb\r");
+        let ret = RespFrame::decode(&mut buf);
+        assert_eq!(ret, Err(RespError::NotComplete));
+        buf.extend_from_slice(b"\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame, VerbatimString::new(*b"mkd", "# a\r\nb").into());
+
+        buf.extend_from_slice(b"=3\r\ntxt\r\n");
+        let ret = VerbatimString::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::InvalidFrame(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_string_decode_zero_copy() -> Result<()> {
+        let blob = vec![b'x'; SHARED_BULK_MIN_LEN];
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\nhello\r\n");
+        buf.extend_from_slice(&BulkString::new(blob.clone()).encode());
+        let range = buf.as_ptr_range();
+
+        let mut parser = RespParser::new();
+        let frame = parser.parse(&mut buf)?;
+        let Some(RespFrame::Array(array)) = frame else {
+            panic!("expect Array, got {:?}", frame);
+        };
+        let [RespFrame::BulkString(small), RespFrame::BulkString(large)] = &array[..] else {
+            panic!("expect two BulkStrings, got {:?}", array);
+        };
+        // a small value gets its own allocation, a large one shares the read buffer
+        assert!(!range.contains(&small.as_ptr()));
+        assert!(range.contains(&large.as_ptr()));
+        assert_eq!(small, &BulkString::new("hello"));
+        assert_eq!(large, &BulkString::new(blob));
         Ok(())
     }
 
@@ -382,6 +901,80 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_array_decode_not_complete() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$5\r\nhel");
+
+        let ret = Array::decode(&mut buf);
+        assert_eq!(ret, Err(RespError::NotComplete));
+        assert_eq!(buf.len(), 20);
+
+        buf.extend_from_slice(b"lo\r\n");
+        let frame = Array::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            Array::new(vec![
+                BulkString::new("get").into(),
+                BulkString::new("hello").into()
+            ])
+        );
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_aggregate_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*3\r\n~2\r\n$3\r\nfoo\r\n*1\r\n$3\r\nbar\r\n*0\r\n:+1\r\n+next\r\n",
+        );
+
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            Array::new(vec![
+                Set::new(vec![
+                    BulkString::new("foo").into(),
+                    Array::new(vec![BulkString::new("bar").into()]).into(),
+                ])
+                .into(),
+                Array::new(vec![]).into(),
+                1.into(),
+            ])
+            .into()
+        );
+        assert_eq!(&buf[..], b"+next\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"%3\r\n+first\r\n:+1\r\n$6\r\nsecond\r\n#t\r\n:+3\r\n*1\r\n+third\r\n",
+        );
+
+        let frame = Map::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            Map::new(vec![
+                (SimpleString::new("first").into(), 1.into()),
+                (BulkString::new("second").into(), true.into()),
+                (
+                    3.into(),
+                    Array::new(vec![SimpleString::new("third").into()]).into()
+                ),
+            ])
+        );
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(b"*1\r\n+first\r\n:+1\r\n");
+        let ret = Map::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::InvalidFrameType(_))));
+        Ok(())
+    }
+
     #[test]
     fn test_set_decode() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -396,6 +989,128 @@ mod tests {
             ])
         );
 
+        // members are unique, duplicates on the wire collapse into one
+        buf.extend_from_slice(b"~3\r\n:+1\r\n,nan\r\n:+1\r\n");
+        let frame = Set::decode(&mut buf)?;
+        assert_eq!(frame.len(), 2);
+        assert_eq!(frame, Set::new(vec![f64::NAN.into(), 1.into()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_hash_and_ord() {
+        let mut frames = HashSet::new();
+        assert!(frames.insert(RespFrame::from(f64::NAN)));
+        assert!(!frames.insert(RespFrame::from(f64::NAN)));
+        assert!(frames.insert(RespFrame::from(0.0)));
+        assert!(!frames.insert(RespFrame::from(-0.0)));
+        assert!(frames.insert(Array::new(vec![1.into(), BulkString::new("a").into()]).into()));
+        assert!(!frames.insert(Array::new(vec![1.into(), BulkString::new("a").into()]).into()));
+        assert_eq!(frames.len(), 3);
+
+        assert!(RespFrame::from(f64::NAN) > RespFrame::from(f64::INFINITY));
+        assert!(RespFrame::from(1) < RespFrame::from(2));
+
+        let numbers = ["-100", "-9", "-0", "+9", "10", "0012345678901234567890"];
+        for pair in numbers.windows(2) {
+            let (a, b) = (
+                BigNumber::new(pair[0]).unwrap(),
+                BigNumber::new(pair[1]).unwrap(),
+            );
+            assert!(a < b, "{:?} < {:?}", a, b);
+        }
+
+        let map = Map::new(vec![
+            (Array::new(vec![1.into()]).into(), true.into()),
+            (1.5.into(), false.into()),
+            (1.5.into(), true.into()),
+        ]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&1.5.into()), Some(&true.into()));
+    }
+
+    #[test]
+    fn test_push_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n+OK\r\n");
+
+        let frame = RespFrame::decode(&mut buf)?;
+        assert!(frame.is_push());
+        assert_eq!(
+            frame,
+            Push::new(vec![
+                BulkString::new("invalidate").into(),
+                Array::new(vec![BulkString::new("foo").into()]).into(),
+            ])
+            .into()
+        );
+
+        let frame = RespFrame::decode(&mut buf)?;
+        assert!(!frame.is_push());
+        Ok(())
+    }
+
+    #[test]
+    fn test_attributed_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"|1\r\n+key-popularity\r\n%2\r\n$1\r\na\r\n,0.1923\r\n$1\r\nb\r\n,0.0012\r\n*2\r\n:2039123\r\n:9543892\r\n");
+
+        let frame = Attributed::decode(&mut buf)?;
+        let attributes = Attribute::new(vec![(
+            SimpleString::new("key-popularity").into(),
+            Map::new(vec![
+                (BulkString::new("a").into(), 0.1923.into()),
+                (BulkString::new("b").into(), 0.0012.into()),
+            ])
+            .into(),
+        )]);
+        assert_eq!(frame.attributes(), &attributes);
+        assert_eq!(
+            *frame,
+            Array::new(vec![2039123.into(), 9543892.into()]).into()
+        );
+        assert!(buf.is_empty());
+
+        // the attribute alone is not a complete frame
+        buf.extend_from_slice(b"|1\r\n+ttl\r\n:+10\r\n");
+        assert_eq!(RespFrame::decode(&mut buf), Err(RespError::NotComplete));
+        buf.extend_from_slice(b"$3\r\nbar\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        let expected = Attributed::new(
+            Attribute::new(vec![(SimpleString::new("ttl").into(), 10.into())]),
+            BulkString::new("bar"),
+        );
+        assert_eq!(frame, expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"$?\r\n;4\r\nHell\r\n;5\r\no wor\r\n;1\r\nd\r\n;0\r\n");
+        let frame = BulkString::decode(&mut buf)?;
+        assert_eq!(frame, BulkString::new("Hello word"));
+
+        buf.extend_from_slice(b"%?\r\n+a\r\n:1\r\n+b\r\n~?\r\n:2\r\n.\r\n.\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            Map::new(vec![
+                (SimpleString::new("a").into(), 1.into()),
+                (
+                    SimpleString::new("b").into(),
+                    Set::new(vec![2.into()]).into()
+                ),
+            ])
+            .into()
+        );
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(b"*?\r\n:1\r\n");
+        assert_eq!(Array::decode(&mut buf), Err(RespError::NotComplete));
+        buf.extend_from_slice(b".\r\n");
+        assert_eq!(Array::decode(&mut buf)?, Array::new(vec![1.into()]));
         Ok(())
     }
 }
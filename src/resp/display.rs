@@ -0,0 +1,246 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+use super::RespFrame;
+
+/// Render the frame the way redis-cli does on a terminal: `(integer) 5`, `(nil)`, quoted
+/// bulk strings, `(error) ...`, numbered and indented aggregate elements, and maps as
+/// `key => value` pairs. Lines are separated by `\n` with no trailing newline.
+impl Display for RespFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_tty(self, 0, f)
+    }
+}
+
+/// Renders a frame like `redis-cli --raw`, see `RespFrame::raw`.
+#[derive(Debug, Clone, Copy)]
+pub struct RawFrame<'a>(&'a RespFrame);
+
+impl RespFrame {
+    /// Render the frame like `redis-cli --raw`: strings unquoted, numbers without their
+    /// type, nil as an empty line and every aggregate element on a line of its own.
+    pub fn raw(&self) -> RawFrame<'_> {
+        RawFrame(self)
+    }
+}
+
+impl Display for RawFrame<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_raw(self.0, f)
+    }
+}
+
+fn write_tty(frame: &RespFrame, indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+    match frame {
+        RespFrame::SimpleString(s) => f.write_str(s),
+        RespFrame::Error(e) => write!(f, "(error) {}", e.0),
+        RespFrame::BulkError(e) => write!(f, "(error) {}", e.0),
+        RespFrame::Integer(n) => write!(f, "(integer) {}", n),
+        RespFrame::BulkString(s) => write_quoted(s, f),
+        RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => {
+            f.write_str("(nil)")
+        }
+        RespFrame::Boolean(b) => write!(f, "({})", b),
+        RespFrame::Double(d) => write!(f, "(double) {}", Double(d.0)),
+        RespFrame::BigNumber(n) => write!(f, "(big number) {}", n.0),
+        // redis-cli shows verbatim text as is, without the format
+        RespFrame::VerbatimString(s) => f.write_str(&String::from_utf8_lossy(&s.data)),
+        RespFrame::Array(a) => write_elements(a.iter(), ')', "(empty array)", indent, f),
+        RespFrame::Set(s) => write_elements(s.iter(), '~', "(empty set)", indent, f),
+        RespFrame::Push(p) => write_elements(p.iter(), ')', "(empty push)", indent, f),
+        RespFrame::Map(m) => {
+            if m.is_empty() {
+                return f.write_str("(empty hash)");
+            }
+            let width = m.len().to_string().len();
+            let inner = indent + width + 2;
+            for (i, (key, value)) in m.iter().enumerate() {
+                write_index(i, width, '#', indent, f)?;
+                write_tty(key, inner, f)?;
+                f.write_str(" => ")?;
+                write_tty(value, inner, f)?;
+            }
+            Ok(())
+        }
+        // attributes are out-of-band metadata, redis-cli only shows the reply
+        RespFrame::Attributed(a) => write_tty(&a.frame, indent, f),
+    }
+}
+
+// every element after the first starts on a new line, indented to line up with the
+// parent element's content
+fn write_elements<'a>(
+    frames: impl ExactSizeIterator<Item = &'a RespFrame>,
+    mark: char,
+    empty: &str,
+    indent: usize,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    if frames.len() == 0 {
+        return f.write_str(empty);
+    }
+    let width = frames.len().to_string().len();
+    for (i, frame) in frames.enumerate() {
+        write_index(i, width, mark, indent, f)?;
+        write_tty(frame, indent + width + 2, f)?;
+    }
+    Ok(())
+}
+
+fn write_index(
+    i: usize,
+    width: usize,
+    mark: char,
+    indent: usize,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    if i > 0 {
+        write!(f, "\n{:indent$}", "")?;
+    }
+    write!(f, "{:>width$}{} ", i + 1, mark)
+}
+
+// quote like redis-cli's sdscatrepr: common escapes, printable ASCII as is and \xHH for
+// everything else
+fn write_quoted(bytes: &[u8], f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_char('"')?;
+    for &b in bytes {
+        match b {
+            b'\\' => f.write_str("\\\\")?,
+            b'"' => f.write_str("\\\"")?,
+            b'\n' => f.write_str("\\n")?,
+            b'\r' => f.write_str("\\r")?,
+            b'\t' => f.write_str("\\t")?,
+            0x07 => f.write_str("\\a")?,
+            0x08 => f.write_str("\\b")?,
+            b' '..=b'~' => f.write_char(b as char)?,
+            _ => write!(f, "\\x{:02x}", b)?,
+        }
+    }
+    f.write_char('"')
+}
+
+fn write_raw(frame: &RespFrame, f: &mut Formatter<'_>) -> fmt::Result {
+    match frame {
+        RespFrame::SimpleString(s) => f.write_str(s),
+        RespFrame::Error(e) => f.write_str(e),
+        RespFrame::BulkError(e) => f.write_str(&e.0),
+        RespFrame::Integer(n) => write!(f, "{}", n),
+        RespFrame::BulkString(s) => f.write_str(&String::from_utf8_lossy(s)),
+        RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => Ok(()),
+        RespFrame::Boolean(b) => write!(f, "({})", b),
+        RespFrame::Double(d) => write!(f, "{}", Double(d.0)),
+        RespFrame::BigNumber(n) => f.write_str(&n.0),
+        RespFrame::VerbatimString(s) => f.write_str(&String::from_utf8_lossy(&s.data)),
+        RespFrame::Array(a) => write_raw_lines(a.iter(), f),
+        RespFrame::Set(s) => write_raw_lines(s.iter(), f),
+        RespFrame::Push(p) => write_raw_lines(p.iter(), f),
+        RespFrame::Map(m) => write_raw_lines(m.iter().flat_map(|(k, v)| [k, v]), f),
+        RespFrame::Attributed(a) => write_raw(&a.frame, f),
+    }
+}
+
+fn write_raw_lines<'a>(
+    frames: impl Iterator<Item = &'a RespFrame>,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    for (i, frame) in frames.enumerate() {
+        if i > 0 {
+            f.write_char('\n')?;
+        }
+        write_raw(frame, f)?;
+    }
+    Ok(())
+}
+
+// the same spelling of the special values as on the wire
+struct Double(f64);
+
+impl Display for Double {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            d if d.is_nan() => f.write_str("nan"),
+            d => write!(f, "{}", d),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Array, BigNumber, BulkError, BulkString, Map, Null, NullBulkString, Set, SimpleError,
+        SimpleString, VerbatimString,
+    };
+
+    #[test]
+    fn test_display_scalars() {
+        let cases: [(RespFrame, &str); 10] = [
+            (SimpleString::new("OK").into(), "OK"),
+            (SimpleError::new("ERR bad").into(), "(error) ERR bad"),
+            (BulkError::new("SYNTAX bad").into(), "(error) SYNTAX bad"),
+            (5.into(), "(integer) 5"),
+            (
+                BulkString::new(&b"a \"b\"\n\xc3\xa9\\"[..]).into(),
+                r#""a \"b\"\n\xc3\xa9\\""#,
+            ),
+            (NullBulkString.into(), "(nil)"),
+            (false.into(), "(false)"),
+            (f64::NAN.into(), "(double) nan"),
+            (BigNumber::new("-12").unwrap().into(), "(big number) -12"),
+            (
+                VerbatimString::new(*b"txt", "Some text").into(),
+                "Some text",
+            ),
+        ];
+        for (frame, expected) in cases {
+            assert_eq!(frame.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_display_aggregates() {
+        let nested: Vec<RespFrame> = (1..=10).map(RespFrame::from).collect();
+        let frame: RespFrame = Array::new(vec![
+            BulkString::new("a").into(),
+            Array::new(nested).into(),
+            Map::new([
+                (BulkString::new("k").into(), Null.into()),
+                (1.into(), Set::new([2.into(), 3.into()]).into()),
+            ])
+            .into(),
+            Array::new(vec![]).into(),
+        ])
+        .into();
+
+        let expected = [
+            "1) \"a\"",
+            "2)  1) (integer) 1",
+            "    2) (integer) 2",
+            "    3) (integer) 3",
+            "    4) (integer) 4",
+            "    5) (integer) 5",
+            "    6) (integer) 6",
+            "    7) (integer) 7",
+            "    8) (integer) 8",
+            "    9) (integer) 9",
+            "   10) (integer) 10",
+            "3) 1# (integer) 1 => 1~ (integer) 2",
+            "      2~ (integer) 3",
+            "   2# \"k\" => (nil)",
+            "4) (empty array)",
+        ];
+        assert_eq!(frame.to_string(), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_display_raw() {
+        let frame: RespFrame = Array::new(vec![
+            BulkString::new("hello world").into(),
+            NullBulkString.into(),
+            Map::new([(SimpleString::new("k").into(), 1.5.into())]).into(),
+            SimpleError::new("ERR bad").into(),
+        ])
+        .into();
+        assert_eq!(frame.raw().to_string(), "hello world\n\nk\n1.5\nERR bad");
+    }
+}
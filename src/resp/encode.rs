@@ -1,6 +1,11 @@
+use std::fmt::Write;
+
+use bytes::BytesMut;
+
 use super::{
-    Array, BulkError, BulkString, Map, Null, NullArray, NullBulkString, RespEncode, Set,
-    SimpleError, SimpleString,
+    Array, Attribute, Attributed, BigNumber, BulkError, BulkString, Map, Null, NullArray,
+    NullBulkString, OrderedFloat, Push, RespEncode, RespFrame, Set, SimpleError, SimpleString,
+    VerbatimString,
 };
 
 /*
@@ -17,142 +22,277 @@ use super::{
     - boolean: "#<t|f>\r\n"
     - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
     - big number: "([+|-]<number>\r\n"
+    - verbatim string: "=<length>\r\n<encoding>:<data>\r\n"
     - map: "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
     - set: "~<number-of-elements>\r\n<element-1>...<element-n>"
+    - push: "><number-of-elements>\r\n<element-1>...<element-n>"
+    - attribute: "|<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n><frame>"
+    - streamed string: "$?\r\n;<length>\r\n<data>\r\n...;0\r\n"
+    - streamed aggregate: "*?\r\n<element-1>...<element-n>.\r\n", also "%?" and "~?"
     - ...
 */
 
 const BUF_CAP: usize = 4096;
 
+// writing into a BytesMut can't fail as it grows on demand
+macro_rules! put {
+    ($buf:expr, $($arg:tt)*) => {
+        $buf.write_fmt(format_args!($($arg)*))
+            .expect("writing to BytesMut never fails")
+    };
+}
+
 // - simple string: "+OK\r\n"
 impl RespEncode for SimpleString {
-    fn encode(self) -> Vec<u8> {
-        format!("+{}\r\n", self.0).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "+{}\r\n", self.0);
     }
 }
 
 // - error: "-Error message\r\n"
 impl RespEncode for SimpleError {
-    fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "-{}\r\n", self.0);
     }
 }
 
 // - bulk error: "!<length>\r\n<error>\r\n"
 impl RespEncode for BulkError {
-    fn encode(self) -> Vec<u8> {
-        format!("!{}\r\n{}\r\n", self.len(), self.0).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "!{}\r\n{}\r\n", self.len(), self.0);
     }
 }
 
 // - integer: ":[<+|->]<value>\r\n"
 impl RespEncode for i64 {
-    fn encode(self) -> Vec<u8> {
-        let sign = if self >= 0 { "+" } else { "-" };
-
-        format!("{}{}\r\n", sign, self.abs()).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, ":{:+}\r\n", self);
     }
 }
 
 // - bulk string: "$<length>\r\n<data>\r\n"
 impl RespEncode for BulkString {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(self.len() + 16);
-        buf.extend_from_slice(&format!("${}\r\n", self.len()).into_bytes());
-        buf.extend_from_slice(&self);
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.reserve(self.len() + 16);
+        put!(buf, "${}\r\n", self.len());
+        buf.extend_from_slice(self);
         buf.extend_from_slice(b"\r\n");
-        buf
     }
 }
 
 // - null bulk string: "$-1\r\n"
 impl RespEncode for NullBulkString {
-    fn encode(self) -> Vec<u8> {
-        b"$-1\r\n".to_vec()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"$-1\r\n");
     }
 }
 
 //     - array: "*<number-of-elements>\r\n<element-1>...<element-n>"
 //        - "*2\r\n$3\r\nget\r\n$5\r\nhello\r\n
 impl RespEncode for Array {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("*{}\r\n", self.len()).into_bytes());
-        for frame in self.0 {
-            buf.extend_from_slice(&frame.encode());
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "*{}\r\n", self.len());
+        for frame in self.iter() {
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
 // - null array: "*-1\r\n"
 impl RespEncode for NullArray {
-    fn encode(self) -> Vec<u8> {
-        b"*-1\r\n".to_vec()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"*-1\r\n");
     }
 }
 
 // - null: "_\r\n"
 impl RespEncode for Null {
-    fn encode(self) -> Vec<u8> {
-        b"_\r\n".to_vec()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"_\r\n");
     }
 }
 
 // - boolean: "#<t|f>\r\n"
 impl RespEncode for bool {
-    fn encode(self) -> Vec<u8> {
-        format!("#{}\r\n", if self { "t" } else { "f" }).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(if *self { b"#t\r\n" } else { b"#f\r\n" });
     }
 }
 
 // - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
+// rust prints the shortest digits that parse back to the same value, the exponent form
+// keeps very large and very small values short
 impl RespEncode for f64 {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32);
-        let ret = if self.abs() > 1e+8 {
-            format!(",{:+e}\r\n", self)
+    fn encode_to(&self, buf: &mut BytesMut) {
+        if self.is_nan() {
+            buf.extend_from_slice(b",nan\r\n");
+        } else if self.is_infinite() {
+            let s: &[u8] = if *self > 0.0 {
+                b",inf\r\n"
+            } else {
+                b",-inf\r\n"
+            };
+            buf.extend_from_slice(s);
+        } else if self.abs() > 1e+8 || (*self != 0.0 && self.abs() < 1e-5) {
+            put!(buf, ",{:+e}\r\n", self);
         } else {
-            let sign = if self >= 0.0 { "+" } else { "-" };
-            format!(",{}{}\r\n", sign, self)
-        };
-        buf.extend_from_slice(&ret.into_bytes());
-        buf
+            put!(buf, ",{:+}\r\n", self);
+        }
+    }
+}
+
+impl RespEncode for OrderedFloat<f64> {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        self.0.encode_to(buf);
+    }
+}
+
+// - big number: "([+|-]<number>\r\n"
+impl RespEncode for BigNumber {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "({}\r\n", self.0);
+    }
+}
+
+// - verbatim string: "=<length>\r\n<encoding>:<data>\r\n"
+impl RespEncode for VerbatimString {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.reserve(self.data.len() + 20);
+        put!(buf, "={}\r\n", self.data.len() + 4);
+        buf.extend_from_slice(&self.format);
+        buf.extend_from_slice(b":");
+        buf.extend_from_slice(&self.data);
+        buf.extend_from_slice(b"\r\n");
     }
 }
 
 // - map : "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
 impl RespEncode for Map {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("%{}\r\n", self.len()).into_bytes());
-        for (key, value) in self.0 {
-            buf.extend_from_slice(&SimpleString::new(key).encode());
-            buf.extend_from_slice(&value.encode());
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "%{}\r\n", self.len());
+        for (key, value) in self.iter() {
+            key.encode_to(buf);
+            value.encode_to(buf);
         }
-        buf
     }
 }
 
 // - set : "~<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncode for Set {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("~{}\r\n", self.len()).into_bytes());
-        for element in self.0 {
-            buf.extend_from_slice(&element.encode());
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "~{}\r\n", self.len());
+        for element in self.iter() {
+            element.encode_to(buf);
         }
-        buf
     }
 }
 
+// - push: "><number-of-elements>\r\n<element-1>...<element-n>"
+impl RespEncode for Push {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, ">{}\r\n", self.len());
+        for element in self.iter() {
+            element.encode_to(buf);
+        }
+    }
+}
+
+// - attribute: "|<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
+impl RespEncode for Attribute {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        put!(buf, "|{}\r\n", self.len());
+        for (key, value) in self.iter() {
+            key.encode_to(buf);
+            value.encode_to(buf);
+        }
+    }
+}
+
+// the attribute goes on the wire right before the frame it describes
+impl RespEncode for Attributed {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        self.attributes.encode_to(buf);
+        self.frame.encode_to(buf);
+    }
+}
+
+// - streamed string: "$?\r\n;<length>\r\n<data>\r\n...;0\r\n"
+impl BulkString {
+    /// Encode as a RESP3 streamed string, so very large values can be sent in
+    /// chunks of at most `chunk_size` bytes.
+    pub fn encode_chunked(&self, chunk_size: usize) -> Vec<u8> {
+        let mut buf = BytesMut::with_capacity(self.len() + BUF_CAP);
+        self.encode_chunked_to(chunk_size, &mut buf);
+        buf.into()
+    }
+
+    pub fn encode_chunked_to(&self, chunk_size: usize, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"$?\r\n");
+        for chunk in self.chunks(chunk_size.max(1)) {
+            put!(buf, ";{}\r\n", chunk.len());
+            buf.extend_from_slice(chunk);
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(b";0\r\n");
+    }
+}
+
+// - streamed aggregate: "*?\r\n<element-1>...<element-n>.\r\n"
+impl Array {
+    pub fn encode_streamed(&self) -> Vec<u8> {
+        let mut buf = BytesMut::with_capacity(BUF_CAP);
+        self.encode_streamed_to(&mut buf);
+        buf.into()
+    }
+
+    pub fn encode_streamed_to(&self, buf: &mut BytesMut) {
+        encode_streamed_to("*", self.iter(), buf);
+    }
+}
+
+impl Set {
+    pub fn encode_streamed(&self) -> Vec<u8> {
+        let mut buf = BytesMut::with_capacity(BUF_CAP);
+        self.encode_streamed_to(&mut buf);
+        buf.into()
+    }
+
+    pub fn encode_streamed_to(&self, buf: &mut BytesMut) {
+        encode_streamed_to("~", self.iter(), buf);
+    }
+}
+
+impl Map {
+    pub fn encode_streamed(&self) -> Vec<u8> {
+        let mut buf = BytesMut::with_capacity(BUF_CAP);
+        self.encode_streamed_to(&mut buf);
+        buf.into()
+    }
+
+    pub fn encode_streamed_to(&self, buf: &mut BytesMut) {
+        let frames = self.iter().flat_map(|(key, value)| [key, value]);
+        encode_streamed_to("%", frames, buf);
+    }
+}
+
+fn encode_streamed_to<'a>(
+    prefix: &str,
+    frames: impl IntoIterator<Item = &'a RespFrame>,
+    buf: &mut BytesMut,
+) {
+    put!(buf, "{}?\r\n", prefix);
+    for frame in frames {
+        frame.encode_to(buf);
+    }
+    buf.extend_from_slice(b".\r\n");
+}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeMap;
-
-    use crate::RespFrame;
+    use bytes::Bytes;
 
     use super::*;
+    use crate::RespError;
 
     #[test]
     fn test_encode_simple_string() {
@@ -187,10 +327,13 @@ mod tests {
     fn test_encode_integer() {
         let frame: RespFrame = 42.into();
         let encoded = frame.encode();
-        assert_eq!(encoded, b"+42\r\n".to_vec());
+        assert_eq!(encoded, b":+42\r\n".to_vec());
         let frame: RespFrame = (-42).into();
         let encoded = frame.encode();
-        assert_eq!(encoded, b"-42\r\n".to_vec());
+        assert_eq!(encoded, b":-42\r\n".to_vec());
+        let frame: RespFrame = i64::MIN.into();
+        let encoded = frame.encode();
+        assert_eq!(encoded, format!(":{}\r\n", i64::MIN).into_bytes());
     }
 
     #[test]
@@ -198,6 +341,15 @@ mod tests {
         let frame: RespFrame = BulkString::new("Hello, world!".to_string()).into();
         let encoded = frame.encode();
         assert_eq!(encoded, b"$13\r\nHello, world!\r\n".to_vec());
+
+        // borrowed data that doesn't live for 'static is copied in
+        let data = String::from("Hello, world!");
+        let frame: RespFrame = BulkString::new(&data[..5]).into();
+        assert_eq!(frame.encode(), b"$5\r\nHello\r\n".to_vec());
+        let frame: RespFrame = BulkString::new(&data.as_bytes()[7..]).into();
+        assert_eq!(frame.encode(), b"$6\r\nworld!\r\n".to_vec());
+        let frame: RespFrame = BulkString::from_bytes(Bytes::from_static(b"hi")).into();
+        assert_eq!(frame.encode(), b"$2\r\nhi\r\n".to_vec());
     }
 
     #[test]
@@ -248,18 +400,58 @@ mod tests {
         let frame: RespFrame = 3.147.into();
         let encoded = frame.encode();
         assert_eq!(encoded, b",+3.147\r\n".to_vec());
+
+        let cases: [(f64, &[u8]); 8] = [
+            (f64::INFINITY, b",inf\r\n"),
+            (f64::NEG_INFINITY, b",-inf\r\n"),
+            (f64::NAN, b",nan\r\n"),
+            (-0.0, b",-0\r\n"),
+            (1.2e300, b",+1.2e300\r\n"),
+            (-0.1, b",-0.1\r\n"),
+            (f64::MIN_POSITIVE / 2.0, b",+1.1125369292536007e-308\r\n"),
+            (5e-324, b",+5e-324\r\n"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(n.encode(), expected.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_encode_big_number() {
+        let frame: RespFrame = BigNumber::from(u128::MAX).into();
+        let encoded = frame.encode();
+        assert_eq!(encoded, format!("({}\r\n", u128::MAX).into_bytes());
+
+        let frame: RespFrame = BigNumber::new("-3492890328409238509324850943850943825024385")
+            .unwrap()
+            .into();
+        let encoded = frame.encode();
+        assert_eq!(
+            encoded,
+            b"(-3492890328409238509324850943850943825024385\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encode_verbatim_string() {
+        let frame: RespFrame = VerbatimString::new(*b"txt", "Some string").into();
+        let encoded = frame.encode();
+        assert_eq!(encoded, b"=15\r\ntxt:Some string\r\n".to_vec());
     }
 
     #[test]
     fn test_encode_map() {
         let pairs = vec![
-            ("key1".to_string(), SimpleString::new("value1").into()),
             (
-                "key2".to_string(),
+                SimpleString::new("key1").into(),
+                SimpleString::new("value1").into(),
+            ),
+            (
+                BulkString::new("key2").into(),
                 BulkString::new("value2".to_string()).into(),
             ),
         ];
-        let frame: RespFrame = Map::new(BTreeMap::from_iter(pairs)).into();
+        let frame: RespFrame = Map::new(pairs).into();
         let encoded = frame.encode();
         println!(
             "test encode map {}",
@@ -267,7 +459,7 @@ mod tests {
         );
         assert_eq!(
             encoded,
-            b"%2\r\n+key1\r\n+value1\r\n+key2\r\n$6\r\nvalue2\r\n".to_vec()
+            b"%2\r\n+key1\r\n+value1\r\n$4\r\nkey2\r\n$6\r\nvalue2\r\n".to_vec()
         );
     }
 
@@ -286,4 +478,109 @@ mod tests {
         );
         assert_eq!(encoded, b"~2\r\n+value1\r\n$6\r\nvalue2\r\n".to_vec());
     }
+
+    #[test]
+    fn test_encode_push() {
+        let frame: RespFrame = Push::new(vec![
+            BulkString::new("message").into(),
+            BulkString::new("channel").into(),
+            BulkString::new("hello").into(),
+        ])
+        .into();
+        assert!(frame.is_push());
+        let encoded = frame.encode();
+        assert_eq!(
+            encoded,
+            b">3\r\n$7\r\nmessage\r\n$7\r\nchannel\r\n$5\r\nhello\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encode_attributed() {
+        let attributes = Attribute::new(vec![(
+            SimpleString::new("key-popularity").into(),
+            Map::new(vec![(BulkString::new("a").into(), 0.1923.into())]).into(),
+        )]);
+        let frame: RespFrame =
+            Attributed::new(attributes, Array::new(vec![RespFrame::Integer(2039123)])).into();
+        let encoded = frame.encode();
+        assert_eq!(
+            encoded,
+            b"|1\r\n+key-popularity\r\n%1\r\n$1\r\na\r\n,+0.1923\r\n*1\r\n:+2039123\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encode_to_by_reference() {
+        let frame: RespFrame = Array::new(vec![
+            BulkString::new("get").into(),
+            Map::new(vec![(SimpleString::new("a").into(), 1.into())]).into(),
+        ])
+        .into();
+
+        let mut buf = BytesMut::from(&b"+OK\r\n"[..]);
+        frame.encode_to(&mut buf);
+        frame.encode_to(&mut buf);
+        let expected = b"*2\r\n$3\r\nget\r\n%1\r\n+a\r\n:+1\r\n";
+        assert_eq!(&buf[..5], b"+OK\r\n");
+        assert_eq!(&buf[5..5 + expected.len()], expected);
+        assert_eq!(&buf[5 + expected.len()..], expected);
+        assert_eq!(frame.encode(), expected.to_vec());
+    }
+
+    #[test]
+    fn test_encode_streamed() {
+        let encoded = BulkString::new("Hello world").encode_chunked(4);
+        assert_eq!(
+            encoded,
+            b"$?\r\n;4\r\nHell\r\n;4\r\no wo\r\n;3\r\nrld\r\n;0\r\n".to_vec()
+        );
+
+        let encoded = Array::new(vec![1.into(), BulkString::new("a").into()]).encode_streamed();
+        assert_eq!(encoded, b"*?\r\n:+1\r\n$1\r\na\r\n.\r\n".to_vec());
+
+        let encoded =
+            Map::new(vec![(SimpleString::new("a").into(), true.into())]).encode_streamed();
+        assert_eq!(encoded, b"%?\r\n+a\r\n#t\r\n.\r\n".to_vec());
+
+        let mut buf = BytesMut::from(&b"+OK\r\n"[..]);
+        Set::new([1.into()]).encode_streamed_to(&mut buf);
+        Array::new(vec![]).encode_streamed_to(&mut buf);
+        assert_eq!(&buf[..], b"+OK\r\n~?\r\n:+1\r\n.\r\n*?\r\n.\r\n");
+    }
+
+    #[test]
+    fn test_simple_line_validation() {
+        assert!(SimpleString::try_new("OK").is_ok());
+        assert_eq!(
+            SimpleString::try_new("OK\r\n+PWNED"),
+            Err(RespError::InvalidSimpleLine("OK\r\n+PWNED".to_string()))
+        );
+        assert!(SimpleError::try_new("ERR bad\nthing").is_err());
+
+        let frame: RespFrame = Array::new(vec![
+            BulkString::new("a\r\nb").into(),
+            Map::new(vec![(1.into(), SimpleError("ERR\r".to_string()).into())]).into(),
+        ])
+        .into();
+        assert_eq!(
+            frame.validate(),
+            Err(RespError::InvalidSimpleLine("ERR\r".to_string()))
+        );
+        let frame: RespFrame = Array::new(vec![BulkString::new("a\r\nb").into()]).into();
+        assert_eq!(frame.validate(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "must not contain CR or LF")]
+    fn test_encode_simple_string_with_crlf() {
+        // the line can't be built, so it never reaches the encoder
+        SimpleString::new("OK\r\n+PWNED").encode();
+    }
+
+    #[test]
+    #[should_panic(expected = "must not contain CR or LF")]
+    fn test_encode_simple_error_with_crlf() {
+        SimpleError::from("ERR a\nb").encode();
+    }
 }
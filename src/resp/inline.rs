@@ -0,0 +1,183 @@
+use bytes::{Buf, BytesMut};
+
+use super::{Array, BulkString, RespError};
+
+/// Decode an inline command such as `SET key "hello world"\r\n` into the same `Array` of
+/// `BulkString`s a multibulk request would carry. Returns `None` until a whole line is
+/// buffered.
+///
+/// Arguments are separated by whitespace and may be quoted the way redis-cli does:
+/// double quotes support `\n`, `\r`, `\t`, `\b`, `\a`, `\\`, `\"` and `\xHH` escapes,
+/// single quotes only support `\'`.
+pub fn decode_inline(buf: &mut BytesMut) -> Result<Option<Array>, RespError> {
+    let Some(end) = buf.iter().position(|&b| b == b'\n') else {
+        return Ok(None);
+    };
+
+    let line = buf.split_to(end);
+    buf.advance(1);
+    let line = line.strip_suffix(b"\r").unwrap_or(&line);
+
+    let args = split_args(line)?
+        .into_iter()
+        .map(|arg| BulkString::new(arg).into())
+        .collect::<Vec<_>>();
+    Ok(Some(Array::new(args)))
+}
+
+fn split_args(line: &[u8]) -> Result<Vec<Vec<u8>>, RespError> {
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = Vec::new();
+        let mut in_double = false;
+        let mut in_single = false;
+        loop {
+            let c = line.get(i).copied();
+            if in_double {
+                match (c, line.get(i + 1).copied()) {
+                    (None, _) => return Err(unbalanced_quotes()),
+                    (Some(b'\\'), Some(b'x'))
+                        if line.len() > i + 3
+                            && line[i + 2].is_ascii_hexdigit()
+                            && line[i + 3].is_ascii_hexdigit() =>
+                    {
+                        arg.push(hex_value(line[i + 2]) * 16 + hex_value(line[i + 3]));
+                        i += 3;
+                    }
+                    (Some(b'\\'), Some(next)) => {
+                        arg.push(match next {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                        i += 1;
+                    }
+                    (Some(b'"'), next) => {
+                        // closing quote must be followed by a space or nothing
+                        if next.is_some_and(|b| !b.is_ascii_whitespace()) {
+                            return Err(unbalanced_quotes());
+                        }
+                        in_double = false;
+                    }
+                    (Some(c), _) => arg.push(c),
+                }
+            } else if in_single {
+                match (c, line.get(i + 1).copied()) {
+                    (None, _) => return Err(unbalanced_quotes()),
+                    (Some(b'\\'), Some(b'\'')) => {
+                        arg.push(b'\'');
+                        i += 1;
+                    }
+                    (Some(b'\''), next) => {
+                        if next.is_some_and(|b| !b.is_ascii_whitespace()) {
+                            return Err(unbalanced_quotes());
+                        }
+                        in_single = false;
+                    }
+                    (Some(c), _) => arg.push(c),
+                }
+            } else {
+                match c {
+                    None => break,
+                    Some(c) if c.is_ascii_whitespace() => break,
+                    Some(b'"') => in_double = true,
+                    Some(b'\'') => in_single = true,
+                    Some(c) => arg.push(c),
+                }
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+fn hex_value(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => c - b'A' + 10,
+    }
+}
+
+fn unbalanced_quotes() -> RespError {
+    RespError::InvalidFrame("unbalanced quotes in inline command".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    fn bulk_strings(args: &[&[u8]]) -> Array {
+        Array::new(
+            args.iter()
+                .map(|arg| BulkString::new(arg.to_vec()).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_decode_inline() -> Result<(), RespError> {
+        let mut buf = BytesMut::from(&b"SET  key value\r\nPING\nGET"[..]);
+
+        let frame = decode_inline(&mut buf)?;
+        assert_eq!(frame, Some(bulk_strings(&[b"SET", b"key", b"value"])));
+        let frame = decode_inline(&mut buf)?;
+        assert_eq!(frame, Some(bulk_strings(&[b"PING"])));
+
+        assert_eq!(decode_inline(&mut buf)?, None);
+        assert_eq!(&buf[..], b"GET");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_inline_quotes() -> Result<(), RespError> {
+        let mut buf =
+            BytesMut::from(&b"set \"hello world\" 'it\\'s' \"a\\r\\n\\x41\\\"\" ''\r\n"[..]);
+
+        let frame = decode_inline(&mut buf)?;
+        assert_eq!(
+            frame,
+            Some(bulk_strings(&[
+                b"set",
+                b"hello world",
+                b"it's",
+                b"a\r\nA\"",
+                b""
+            ]))
+        );
+
+        for line in [
+            &b"set \"foo\r\n"[..],
+            b"set 'foo\r\n",
+            b"set \"foo\"bar\r\n",
+        ] {
+            let mut buf = BytesMut::from(line);
+            assert!(matches!(
+                decode_inline(&mut buf),
+                Err(RespError::InvalidFrame(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_inline_empty_line() -> Result<(), RespError> {
+        let mut buf = BytesMut::from(&b"   \r\n"[..]);
+        assert_eq!(decode_inline(&mut buf)?, Some(Array::new(vec![])));
+        assert!(buf.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+/*
+Mapping between frames and JSON, used by the HTTP gateway:
+
+    frame                               JSON
+    simple string, bulk string          string
+    non-UTF-8 bulk or verbatim string   {"$base64": "<standard base64 with padding>"}
+    simple error, bulk error            {"$error": "<message>"}
+    integer                             number
+    big number                          number if it fits in an i64 or u64, else string
+    double                              number, or "inf", "-inf" and "nan" as strings
+    boolean                             bool
+    null, null bulk string, null array  null
+    verbatim string                     string, the format is dropped
+    array, set, push                    array
+    map                                 object, keys that aren't strings use their
+                                        JSON text, e.g. 1 becomes "1"
+    attributed frame                    the frame, attributes are dropped
+
+Going back, strings become bulk strings, numbers become integers (big numbers above
+i64::MAX) or doubles, objects become maps with bulk string keys, and an object whose only
+key is "$error" or "$base64" is turned back into the error or the binary bulk string.
+*/
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{Map as JsonMap, Number, Value};
+
+use super::{
+    Array, BigNumber, BulkError, BulkString, Map, Null, RespError, RespFrame, SimpleError,
+};
+
+const ERROR_TAG: &str = "$error";
+const BASE64_TAG: &str = "$base64";
+
+impl From<&RespFrame> for Value {
+    fn from(frame: &RespFrame) -> Self {
+        match frame {
+            RespFrame::SimpleString(s) => Value::String(s.0.clone()),
+            RespFrame::Error(e) => tagged(ERROR_TAG, e.0.clone()),
+            RespFrame::BulkError(e) => tagged(ERROR_TAG, e.0.clone()),
+            RespFrame::Integer(n) => (*n).into(),
+            RespFrame::BulkString(s) => bytes_to_json(s),
+            RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => {
+                Value::Null
+            }
+            RespFrame::Boolean(b) => Value::Bool(*b),
+            RespFrame::Double(d) => match Number::from_f64(d.0) {
+                Some(n) => Value::Number(n),
+                None if d.0.is_nan() => "nan".into(),
+                None if d.0 > 0.0 => "inf".into(),
+                None => "-inf".into(),
+            },
+            RespFrame::BigNumber(n) => {
+                if let Ok(n) = i64::try_from(n) {
+                    n.into()
+                } else if let Ok(n) = n.parse::<u64>() {
+                    n.into()
+                } else {
+                    Value::String(n.0.clone())
+                }
+            }
+            RespFrame::VerbatimString(s) => bytes_to_json(&s.data),
+            RespFrame::Array(a) => a.iter().map(Value::from).collect(),
+            RespFrame::Set(s) => s.iter().map(Value::from).collect(),
+            RespFrame::Push(p) => p.iter().map(Value::from).collect(),
+            RespFrame::Map(m) => {
+                let object = m
+                    .iter()
+                    .map(|(key, value)| (json_key(key), value.into()))
+                    .collect::<JsonMap<_, _>>();
+                Value::Object(object)
+            }
+            RespFrame::Attributed(a) => a.frame.as_ref().into(),
+        }
+    }
+}
+
+impl From<RespFrame> for Value {
+    fn from(frame: RespFrame) -> Self {
+        (&frame).into()
+    }
+}
+
+impl TryFrom<Value> for RespFrame {
+    type Error = RespError;
+
+    fn try_from(value: Value) -> Result<Self, RespError> {
+        let frame = match value {
+            Value::Null => Null.into(),
+            Value::Bool(b) => b.into(),
+            Value::Number(n) => {
+                if let Some(n) = n.as_i64() {
+                    n.into()
+                } else if let Some(n) = n.as_u64() {
+                    BigNumber::from(n).into()
+                } else {
+                    n.as_f64().unwrap_or(f64::NAN).into()
+                }
+            }
+            Value::String(s) => BulkString::new(s).into(),
+            Value::Array(values) => {
+                let frames = values
+                    .into_iter()
+                    .map(RespFrame::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Array::new(frames).into()
+            }
+            Value::Object(object) => object_to_frame(object)?,
+        };
+        Ok(frame)
+    }
+}
+
+fn object_to_frame(object: JsonMap<String, Value>) -> Result<RespFrame, RespError> {
+    if object.len() == 1 {
+        match object.iter().next() {
+            Some((tag, Value::String(msg))) if tag == ERROR_TAG => {
+                // a simple error can't hold a line break, a bulk error can
+                return Ok(match SimpleError::try_new(msg.as_str()) {
+                    Ok(e) => e.into(),
+                    Err(_) => BulkError::new(msg.as_str()).into(),
+                });
+            }
+            Some((tag, Value::String(data))) if tag == BASE64_TAG => {
+                let bytes = STANDARD
+                    .decode(data)
+                    .map_err(|_| RespError::InvalidConversion {
+                        expected: "base64 data",
+                        found: format!("string {:?}", data),
+                    })?;
+                return Ok(BulkString::new(bytes).into());
+            }
+            _ => {}
+        }
+    }
+
+    let pairs = object
+        .into_iter()
+        .map(|(key, value)| Ok((BulkString::new(key).into(), value.try_into()?)))
+        .collect::<Result<Vec<_>, RespError>>()?;
+    Ok(Map::new(pairs).into())
+}
+
+fn tagged(tag: &str, value: impl Into<Value>) -> Value {
+    let mut object = JsonMap::new();
+    object.insert(tag.to_string(), value.into());
+    Value::Object(object)
+}
+
+fn bytes_to_json(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Value::String(s.to_string()),
+        Err(_) => tagged(BASE64_TAG, STANDARD.encode(bytes)),
+    }
+}
+
+// JSON keys must be strings, other frames use their JSON text
+fn json_key(key: &RespFrame) -> String {
+    match Value::from(key) {
+        Value::String(s) => s,
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{NullBulkString, Set, SimpleString, VerbatimString};
+
+    #[test]
+    fn test_frame_to_json() {
+        let frame: RespFrame = Array::new(vec![
+            SimpleString::new("OK").into(),
+            BulkString::new(vec![0xff, 0x00, 0x61]).into(),
+            SimpleError::new("ERR bad").into(),
+            BigNumber::from(u64::MAX).into(),
+            BigNumber::from(i128::MAX).into(),
+            f64::INFINITY.into(),
+            0.5.into(),
+            NullBulkString.into(),
+            VerbatimString::new(*b"txt", "text").into(),
+            Set::new([1.into(), 2.into()]).into(),
+            Map::new([
+                (1.into(), true.into()),
+                (BulkString::new("k").into(), Null.into()),
+            ])
+            .into(),
+        ])
+        .into();
+
+        let expected = json!([
+            "OK",
+            { "$base64": "/wBh" },
+            { "$error": "ERR bad" },
+            u64::MAX,
+            i128::MAX.to_string(),
+            "inf",
+            0.5,
+            null,
+            "text",
+            [1, 2],
+            { "1": true, "k": null },
+        ]);
+        assert_eq!(Value::from(frame), expected);
+    }
+
+    #[test]
+    fn test_json_to_frame() -> Result<(), RespError> {
+        let value = json!({
+            "name": "alice",
+            "bin": { "$base64": "/wBh" },
+            "err": { "$error": "ERR bad" },
+            "multiline": { "$error": "ERR a\nb" },
+            "nums": [1, u64::MAX, 1.5],
+            "none": null,
+        });
+        let expected: RespFrame = Map::new([
+            (
+                BulkString::new("name").into(),
+                BulkString::new("alice").into(),
+            ),
+            (
+                BulkString::new("bin").into(),
+                BulkString::new(vec![0xff, 0x00, 0x61]).into(),
+            ),
+            (
+                BulkString::new("err").into(),
+                SimpleError::new("ERR bad").into(),
+            ),
+            (
+                BulkString::new("multiline").into(),
+                BulkError::new("ERR a\nb").into(),
+            ),
+            (
+                BulkString::new("nums").into(),
+                Array::new(vec![1.into(), BigNumber::from(u64::MAX).into(), 1.5.into()]).into(),
+            ),
+            (BulkString::new("none").into(), Null.into()),
+        ])
+        .into();
+        assert_eq!(RespFrame::try_from(value.clone())?, expected);
+
+        // a tagged value survives the round trip
+        assert_eq!(
+            Value::from(RespFrame::try_from(value.clone())?)["bin"],
+            value["bin"]
+        );
+
+        let ret = RespFrame::try_from(json!({ "$base64": "not base64!" }));
+        assert!(matches!(ret, Err(RespError::InvalidConversion { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,583 @@
+use bytes::BytesMut;
+
+use super::{decode::parse_frame, decode_inline, RespError, RespFrame};
+
+/// Protocol limits enforced while scanning, mirroring Redis' `proto-max-bulk-len`.
+///
+/// Lengths announced on the wire are checked before anything is buffered or allocated
+/// for them, so a hostile peer can't make the decoder wait for or reserve huge frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    // max length of a bulk string, bulk error or verbatim string, streamed or not
+    pub max_bulk_len: usize,
+    // max number of elements (or map entries) in one aggregate
+    pub max_aggregate_len: usize,
+    // max number of nested aggregates
+    pub max_depth: usize,
+    // max length of an inline command line
+    pub max_inline_len: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_aggregate_len: i32::MAX as usize,
+            max_depth: 128,
+            max_inline_len: 64 * 1024,
+        }
+    }
+}
+
+/// How invalid UTF-8 in simple strings and simple errors is handled while decoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Utf8Mode {
+    /// Reject the frame with `RespError::InvalidUtf8`.
+    #[default]
+    Strict,
+    /// Replace invalid sequences with U+FFFD, like `String::from_utf8_lossy`.
+    Lossy,
+}
+
+/// A resumable scanner that finds the boundary of the next frame in a buffer.
+///
+/// The parser walks the buffer once, keeping the offset it reached and a stack of
+/// remaining element counts for the aggregates it is inside. When the frame is not
+/// fully received yet, calling `scan` again with more data resumes from where it
+/// stopped instead of rescanning from the start of the frame.
+#[derive(Debug, Default)]
+pub struct RespParser {
+    limits: DecodeLimits,
+    utf8_mode: Utf8Mode,
+    // offset of the next element header inside the current frame
+    pos: usize,
+    // offset we already searched for CRLF when waiting on a long line
+    searched: usize,
+    // every open aggregate or streamed string, innermost last
+    pending: Vec<Pending>,
+}
+
+#[derive(Debug)]
+enum Pending {
+    // remaining elements of a sized aggregate
+    Elements(usize),
+    // streamed aggregate with the number of elements seen, closed by ".\r\n"
+    Aggregate(usize),
+    // streamed string with the number of bytes seen, closed by ";0\r\n"
+    Chunks(usize),
+}
+
+impl RespParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: DecodeLimits) -> Self {
+        RespParser {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+
+    pub fn utf8_mode(mut self, mode: Utf8Mode) -> Self {
+        self.utf8_mode = mode;
+        self
+    }
+
+    /// Scan `buf` and return the length of the first complete frame, or `None` if more
+    /// data is needed. The buffer must start with the same frame on every call until a
+    /// length is returned.
+    pub fn scan(&mut self, buf: &[u8]) -> Result<Option<usize>, RespError> {
+        let ret = self.scan_frame(buf);
+        if ret.is_err() {
+            // don't resume a broken frame on the next call
+            self.reset();
+        }
+        ret
+    }
+
+    fn scan_frame(&mut self, buf: &[u8]) -> Result<Option<usize>, RespError> {
+        loop {
+            let Some(&prefix) = buf.get(self.pos) else {
+                return Ok(None);
+            };
+
+            let Some(line_end) = self.find_line_end(buf) else {
+                return Ok(None);
+            };
+            let next = line_end + CRLF_LEN;
+
+            if matches!(self.pending.last(), Some(Pending::Chunks(_))) && prefix != b';' {
+                return Err(RespError::InvalidFrame(
+                    "streamed string chunk must start with ';'".to_string(),
+                ));
+            }
+
+            match prefix {
+                b'+' | b'-' => {
+                    let line = &buf[self.pos + 1..line_end];
+                    // a bare CR or LF can't be encoded back, see `RespFrame::validate`
+                    if line.iter().any(|b| matches!(b, b'\r' | b'\n')) {
+                        return Err(RespError::InvalidSimpleLine(
+                            String::from_utf8_lossy(line).into_owned(),
+                        ));
+                    }
+                    if self.utf8_mode == Utf8Mode::Strict {
+                        std::str::from_utf8(line)?;
+                    }
+                    self.pos = next;
+                }
+                b':' | b'#' | b',' | b'(' | b'_' => self.pos = next,
+                b'$' if &buf[self.pos + 1..line_end] == b"?" => {
+                    self.open(Pending::Chunks(0))?;
+                    self.pos = next;
+                    self.searched = next;
+                    continue;
+                }
+                b';' => {
+                    let len = checked_length(parse_header(buf, self.pos, line_end)?)?;
+                    let Some(Pending::Chunks(total)) = self.pending.last_mut() else {
+                        return Err(RespError::InvalidFrame(
+                            "chunk outside of a streamed string".to_string(),
+                        ));
+                    };
+                    if len > self.limits.max_bulk_len - *total {
+                        return Err(RespError::BulkLengthExceeded(total.saturating_add(len)));
+                    }
+                    if len == 0 {
+                        // the zero length chunk ends the string
+                        self.pending.pop();
+                        self.pos = next;
+                    } else {
+                        if !has_data(buf, next, len) {
+                            return Ok(None);
+                        }
+                        let end = next + len;
+                        if &buf[end..end + CRLF_LEN] != CRLF {
+                            return Err(RespError::InvalidFrame(
+                                "chunk data is not terminated by CRLF".to_string(),
+                            ));
+                        }
+                        *total += len;
+                        self.pos = end + CRLF_LEN;
+                        self.searched = self.pos;
+                        continue;
+                    }
+                }
+                b'.' => {
+                    if line_end != self.pos + 1
+                        || !matches!(self.pending.last(), Some(Pending::Aggregate(_)))
+                    {
+                        return Err(RespError::InvalidFrame(
+                            "unexpected end of streamed aggregate".to_string(),
+                        ));
+                    }
+                    self.pending.pop();
+                    self.pos = next;
+                }
+                b'*' | b'~' | b'%' if &buf[self.pos + 1..line_end] == b"?" => {
+                    self.open(Pending::Aggregate(0))?;
+                    self.pos = next;
+                    self.searched = next;
+                    continue;
+                }
+                b'$' | b'!' | b'=' => {
+                    let len = parse_header(buf, self.pos, line_end)?;
+                    if prefix == b'$' && is_null_length(buf, self.pos, line_end) {
+                        self.pos = next;
+                    } else {
+                        let len = checked_length(len)?;
+                        if len > self.limits.max_bulk_len {
+                            return Err(RespError::BulkLengthExceeded(len));
+                        }
+                        if !has_data(buf, next, len) {
+                            return Ok(None);
+                        }
+                        let end = next + len;
+                        if &buf[end..end + CRLF_LEN] != CRLF {
+                            return Err(RespError::InvalidFrame(
+                                "bulk data is not terminated by CRLF".to_string(),
+                            ));
+                        }
+                        self.pos = end + CRLF_LEN;
+                    }
+                }
+                b'*' | b'~' | b'%' | b'>' | b'|' => {
+                    let len = parse_header(buf, self.pos, line_end)?;
+                    let null = prefix == b'*' && is_null_length(buf, self.pos, line_end);
+                    self.pos = next;
+                    self.searched = next;
+                    if !null {
+                        let len = checked_length(len)?;
+                        if len > self.limits.max_aggregate_len {
+                            return Err(RespError::AggregateLengthExceeded(len));
+                        }
+                        let elements = match prefix {
+                            b'%' => len.checked_mul(2),
+                            // an attribute is followed by the frame it belongs to
+                            b'|' => len.checked_mul(2).and_then(|n| n.checked_add(1)),
+                            _ => Some(len),
+                        }
+                        .ok_or(RespError::AggregateLengthExceeded(len))?;
+                        if elements > 0 {
+                            self.open(Pending::Elements(elements))?;
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    return Err(RespError::InvalidFrame(format!(
+                        "invalid frame type {:?}",
+                        prefix as char
+                    )))
+                }
+            }
+            self.searched = self.pos;
+
+            if self.complete_element()? {
+                let len = self.pos;
+                self.reset();
+                return Ok(Some(len));
+            }
+        }
+    }
+
+    /// Scan `buf` and split off and decode the first frame once it is complete.
+    pub fn parse(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        match self.scan(buf)? {
+            Some(len) => {
+                let mut data = buf.split_to(len);
+                parse_frame(&mut data).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Like `parse`, but for requests sent to a server: a line that does not start with a
+    /// RESP type byte is decoded as an inline command, and empty lines are skipped.
+    pub fn parse_request(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        loop {
+            match buf.first() {
+                Some(b) if !RESP_TYPES.contains(b) => {
+                    let max = self.limits.max_inline_len;
+                    // resume the search for the end of the line where the last call
+                    // stopped, so a slowly received line is scanned once
+                    let end = buf.len().min(max.saturating_add(1));
+                    let start = self.searched.min(end);
+                    if !buf[start..end].contains(&b'\n') {
+                        if buf.len() > max {
+                            self.reset();
+                            return Err(RespError::InlineLengthExceeded(max));
+                        }
+                        self.searched = end;
+                        return Ok(None);
+                    }
+                    self.reset();
+                    match decode_inline(buf)? {
+                        Some(array) if array.is_empty() => continue,
+                        Some(array) => return Ok(Some(array.into())),
+                        None => return Ok(None),
+                    }
+                }
+                _ => return self.parse(buf),
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.searched = 0;
+        self.pending.clear();
+    }
+
+    fn open(&mut self, pending: Pending) -> Result<(), RespError> {
+        if self.pending.len() >= self.limits.max_depth {
+            return Err(RespError::NestingDepthExceeded(self.limits.max_depth));
+        }
+        self.pending.push(pending);
+        Ok(())
+    }
+
+    // account for a finished element, returns true when the whole frame is finished
+    fn complete_element(&mut self) -> Result<bool, RespError> {
+        while let Some(pending) = self.pending.last_mut() {
+            match pending {
+                Pending::Elements(remained) => {
+                    *remained -= 1;
+                    if *remained > 0 {
+                        return Ok(false);
+                    }
+                }
+                // streamed aggregates only end on their terminator
+                Pending::Aggregate(count) => {
+                    *count += 1;
+                    if *count > self.limits.max_aggregate_len {
+                        return Err(RespError::AggregateLengthExceeded(*count));
+                    }
+                    return Ok(false);
+                }
+                Pending::Chunks(_) => unreachable!("a streamed string only holds chunks"),
+            }
+            // the aggregate itself is now an element of its parent
+            self.pending.pop();
+        }
+        Ok(true)
+    }
+
+    fn find_line_end(&mut self, buf: &[u8]) -> Option<usize> {
+        let start = self.searched.max(self.pos + 1);
+        match buf[start.min(buf.len())..]
+            .windows(CRLF_LEN)
+            .position(|w| w == CRLF)
+        {
+            Some(i) => Some(start + i),
+            None => {
+                // keep the last byte as it may be the '\r' of a split CRLF
+                self.searched = buf.len().saturating_sub(1).max(self.pos + 1);
+                None
+            }
+        }
+    }
+}
+
+const RESP_TYPES: &[u8] = b"+-:$*_#,(=%~>|!";
+const CRLF: &[u8] = b"\r\n";
+const CRLF_LEN: usize = CRLF.len();
+
+fn parse_header(buf: &[u8], start: usize, end: usize) -> Result<isize, RespError> {
+    let s = String::from_utf8_lossy(&buf[start + 1..end]);
+    Ok(s.parse()?)
+}
+
+// only "-1" marks a null, the decoder doesn't accept other spellings like "-01"
+fn is_null_length(buf: &[u8], start: usize, end: usize) -> bool {
+    &buf[start + 1..end] == b"-1"
+}
+
+// whether `len` bytes of data and their CRLF follow `start`, without overflowing on huge
+// announced lengths
+fn has_data(buf: &[u8], start: usize, len: usize) -> bool {
+    buf.len().saturating_sub(start) >= len.saturating_add(CRLF_LEN)
+}
+
+fn checked_length(len: isize) -> Result<usize, RespError> {
+    usize::try_from(len).map_err(|_| RespError::InvalidFrameLength(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Array, BulkString, SimpleError, SimpleString};
+
+    #[test]
+    fn test_scan_resumes_partial_frame() -> Result<(), RespError> {
+        let data = b"*2\r\n$5\r\nhello\r\n*2\r\n:1\r\n+OK\r\n";
+        let mut parser = RespParser::new();
+        for i in 0..data.len() {
+            assert_eq!(parser.scan(&data[..i])?, None);
+        }
+        assert_eq!(parser.scan(data)?, Some(data.len()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_nested_aggregates() -> Result<(), RespError> {
+        let mut parser = RespParser::new();
+        let data = b"*3\r\n~1\r\n$3\r\nfoo\r\n*0\r\n*-1\r\n+next\r\n";
+        assert_eq!(parser.scan(data)?, Some(data.len() - 7));
+
+        let data = b"%1\r\n+key\r\n*1\r\n$-1\r\n";
+        assert_eq!(parser.scan(data)?, Some(data.len()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_streamed() -> Result<(), RespError> {
+        let data = b"*?\r\n$?\r\n;4\r\nHell\r\n;1\r\no\r\n;0\r\n%?\r\n+a\r\n:1\r\n.\r\n.\r\n";
+        let mut parser = RespParser::new();
+        for i in 0..data.len() {
+            assert_eq!(parser.scan(&data[..i])?, None);
+        }
+        assert_eq!(parser.scan(data)?, Some(data.len()));
+
+        let mut parser = RespParser::new();
+        assert!(parser.scan(b"$?\r\n+OK\r\n").is_err());
+        let mut parser = RespParser::new();
+        assert!(parser.scan(b"*1\r\n.\r\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_invalid_frame() {
+        let mut parser = RespParser::new();
+        assert!(parser.scan(b"?\r\n").is_err());
+        assert_eq!(
+            parser.scan(b"$-2\r\n"),
+            Err(RespError::InvalidFrameLength(-2))
+        );
+        assert!(parser.scan(b"$3\r\nfoobar\r\n").is_err());
+        assert_eq!(
+            parser.scan(b"*-01\r\n"),
+            Err(RespError::InvalidFrameLength(-1))
+        );
+        assert_eq!(
+            parser.scan(b"$-01\r\n"),
+            Err(RespError::InvalidFrameLength(-1))
+        );
+        assert_eq!(parser.scan(b"*1\r\n$-1\r\n"), Ok(Some(9)));
+        assert_eq!(
+            parser.scan(b"+a\nb\r\n"),
+            Err(RespError::InvalidSimpleLine("a\nb".to_string()))
+        );
+        assert_eq!(
+            parser.scan(b"*1\r\n-ERR a\r\r\n"),
+            Err(RespError::InvalidSimpleLine("ERR a\r".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_pipelined_frames() -> Result<(), RespError> {
+        let mut parser = RespParser::new();
+        let mut buf = BytesMut::from(&b"*1\r\n$3\r\nget\r\n*1\r\n$3\r\nfo"[..]);
+
+        let frame = parser.parse(&mut buf)?;
+        let expected: RespFrame = Array::new(vec![BulkString::new("get").into()]).into();
+        assert_eq!(frame, Some(expected));
+
+        assert_eq!(parser.parse(&mut buf)?, None);
+        buf.extend_from_slice(b"o\r\n");
+        let frame = parser.parse(&mut buf)?;
+        let expected: RespFrame = Array::new(vec![BulkString::new("foo").into()]).into();
+        assert_eq!(frame, Some(expected));
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_request_inline() -> Result<(), RespError> {
+        let mut parser = RespParser::new();
+        let mut buf = BytesMut::from(&b"\r\nget hello\r\n*1\r\n$4\r\nPING\r\n"[..]);
+
+        let frame = parser.parse_request(&mut buf)?;
+        let expected = Array::new(vec![
+            BulkString::new("get").into(),
+            BulkString::new("hello").into(),
+        ]);
+        assert_eq!(frame, Some(expected.into()));
+
+        let frame = parser.parse_request(&mut buf)?;
+        let expected = Array::new(vec![BulkString::new("PING").into()]);
+        assert_eq!(frame, Some(expected.into()));
+        assert!(buf.is_empty());
+
+        // a line received in pieces is searched from where the last call stopped
+        buf.extend_from_slice(b"get hel");
+        assert_eq!(parser.parse_request(&mut buf)?, None);
+        assert_eq!(parser.searched, 7);
+        buf.extend_from_slice(b"lo\r\n");
+        let frame = parser.parse_request(&mut buf)?;
+        let expected = Array::new(vec![
+            BulkString::new("get").into(),
+            BulkString::new("hello").into(),
+        ]);
+        assert_eq!(frame, Some(expected.into()));
+        assert_eq!(parser.searched, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_limits() {
+        let limits = DecodeLimits {
+            max_bulk_len: 8,
+            max_aggregate_len: 2,
+            max_depth: 2,
+            max_inline_len: 8,
+        };
+
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(
+            parser.scan(b"$9\r\n"),
+            Err(RespError::BulkLengthExceeded(9))
+        );
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(
+            parser.scan(b"$?\r\n;5\r\nhello\r\n;4\r\n"),
+            Err(RespError::BulkLengthExceeded(9))
+        );
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(
+            parser.scan(b"*999999999999\r\n"),
+            Err(RespError::AggregateLengthExceeded(999999999999))
+        );
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(
+            parser.scan(b"~?\r\n:1\r\n:2\r\n:3\r\n"),
+            Err(RespError::AggregateLengthExceeded(3))
+        );
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(
+            parser.scan(b"*1\r\n*1\r\n*1\r\n"),
+            Err(RespError::NestingDepthExceeded(2))
+        );
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(parser.scan(b"*1\r\n*1\r\n:1\r\n"), Ok(Some(12)));
+
+        let mut parser = RespParser::with_limits(limits);
+        let mut buf = BytesMut::from(&b"get hello"[..]);
+        assert_eq!(
+            parser.parse_request(&mut buf),
+            Err(RespError::InlineLengthExceeded(8))
+        );
+        let mut buf = BytesMut::from(&b"get foo\r\n"[..]);
+        assert!(parser.parse_request(&mut buf).is_ok());
+    }
+
+    #[test]
+    fn test_scan_unbounded_limits() {
+        let limits = DecodeLimits {
+            max_bulk_len: usize::MAX,
+            max_aggregate_len: usize::MAX,
+            max_depth: usize::MAX,
+            max_inline_len: usize::MAX,
+        };
+
+        // lengths up to i64::MAX must not overflow the offset arithmetic
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(parser.scan(b"$9223372036854775807\r\nab"), Ok(None));
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(parser.scan(b"$?\r\n;9223372036854775807\r\nab"), Ok(None));
+        let mut parser = RespParser::with_limits(limits);
+        assert_eq!(parser.scan(b"%9223372036854775807\r\n:1\r\n"), Ok(None));
+
+        let mut parser = RespParser::with_limits(limits);
+        let mut buf = BytesMut::from(&b"get foo"[..]);
+        assert_eq!(parser.parse_request(&mut buf), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_utf8_mode() -> Result<(), RespError> {
+        let data = b"*2\r\n+caf\xc3\xa9\r\n-ERR \xff\r\n";
+
+        let mut parser = RespParser::new();
+        let mut buf = BytesMut::from(&data[..]);
+        assert!(matches!(
+            parser.parse(&mut buf),
+            Err(RespError::InvalidUtf8(_))
+        ));
+
+        let mut parser = RespParser::new().utf8_mode(Utf8Mode::Lossy);
+        let mut buf = BytesMut::from(&data[..]);
+        let frame = parser.parse(&mut buf)?;
+        let expected: RespFrame = Array::new(vec![
+            SimpleString::new("café").into(),
+            SimpleError::new("ERR \u{fffd}").into(),
+        ])
+        .into();
+        assert_eq!(frame, Some(expected));
+        Ok(())
+    }
+}
@@ -0,0 +1,428 @@
+use serde::{ser, Serialize};
+
+use super::{Array, BigNumber, BulkString, Map, Null, RespEncode, RespError, RespFrame};
+
+/// Serialize any `Serialize` value into a frame.
+///
+/// Strings, chars and bytes become bulk strings, integers that don't fit in an `i64`
+/// become big numbers, sequences and tuples become arrays, maps and structs become maps
+/// keyed by field name, and `None` and `()` become null. Enum variants follow the
+/// externally tagged layout: a unit variant is its name, any other variant is a map with
+/// the name as the only key.
+pub fn to_frame<T: Serialize + ?Sized>(value: &T) -> Result<RespFrame, RespError> {
+    value.serialize(FrameSerializer)
+}
+
+/// Serialize any `Serialize` value straight to its RESP3 encoding, see `to_frame`.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, RespError> {
+    Ok(to_frame(value)?.encode())
+}
+
+/// A serializer whose output is a `RespFrame`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameSerializer;
+
+impl ser::Error for RespError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        RespError::Serde(msg.to_string())
+    }
+}
+
+impl ser::Serializer for FrameSerializer {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArrayVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMapVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<RespFrame, RespError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<RespFrame, RespError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<RespFrame, RespError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<RespFrame, RespError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<RespFrame, RespError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<RespFrame, RespError> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(v.into()),
+            Err(_) => Ok(BigNumber::from(v).into()),
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<RespFrame, RespError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<RespFrame, RespError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<RespFrame, RespError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<RespFrame, RespError> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<RespFrame, RespError> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(v.into()),
+            Err(_) => Ok(BigNumber::from(v).into()),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<RespFrame, RespError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<RespFrame, RespError> {
+        Ok(v.into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<RespFrame, RespError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<RespFrame, RespError> {
+        Ok(BulkString::from(v).into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<RespFrame, RespError> {
+        Ok(BulkString::new(v.to_vec()).into())
+    }
+
+    fn serialize_none(self) -> Result<RespFrame, RespError> {
+        Ok(Null.into())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<RespFrame, RespError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<RespFrame, RespError> {
+        Ok(Null.into())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<RespFrame, RespError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<RespFrame, RespError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<RespFrame, RespError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<RespFrame, RespError> {
+        Ok(tagged(variant, to_frame(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, RespError> {
+        Ok(SerializeArray(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, RespError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, RespError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArrayVariant, RespError> {
+        Ok(SerializeArrayVariant {
+            variant,
+            array: SerializeArray(Vec::with_capacity(len)),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, RespError> {
+        Ok(SerializeMap::default())
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, RespError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMapVariant, RespError> {
+        Ok(SerializeMapVariant {
+            variant,
+            map: SerializeMap::default(),
+        })
+    }
+}
+
+// externally tagged enum variant: a map with the variant name as the only key
+fn tagged(variant: &str, value: RespFrame) -> RespFrame {
+    Map::new([(BulkString::from(variant).into(), value)]).into()
+}
+
+pub struct SerializeArray(Vec<RespFrame>);
+
+pub struct SerializeArrayVariant {
+    variant: &'static str,
+    array: SerializeArray,
+}
+
+#[derive(Default)]
+pub struct SerializeMap {
+    pairs: Vec<(RespFrame, RespFrame)>,
+    next_key: Option<RespFrame>,
+}
+
+pub struct SerializeMapVariant {
+    variant: &'static str,
+    map: SerializeMap,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RespError> {
+        self.0.push(to_frame(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, RespError> {
+        Ok(Array::new(self.0).into())
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RespError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RespFrame, RespError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RespError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RespFrame, RespError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArrayVariant {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RespError> {
+        ser::SerializeSeq::serialize_element(&mut self.array, value)
+    }
+
+    fn end(self) -> Result<RespFrame, RespError> {
+        let value = ser::SerializeSeq::end(self.array)?;
+        Ok(tagged(self.variant, value))
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), RespError> {
+        // any frame can be a key, not only strings
+        self.next_key = Some(to_frame(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RespError> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| RespError::Serde("map value without a key".to_string()))?;
+        self.pairs.push((key, to_frame(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, RespError> {
+        Ok(Map::new(self.pairs).into())
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), RespError> {
+        self.pairs
+            .push((BulkString::from(key).into(), to_frame(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, RespError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMapVariant {
+    type Ok = RespFrame;
+    type Error = RespError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), RespError> {
+        ser::SerializeStruct::serialize_field(&mut self.map, key, value)
+    }
+
+    fn end(self) -> Result<RespFrame, RespError> {
+        let value = ser::SerializeMap::end(self.map)?;
+        Ok(tagged(self.variant, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    enum Kind {
+        Plain,
+        Tagged(u8),
+        Pair(i32, bool),
+    }
+
+    #[derive(Serialize)]
+    struct User {
+        name: String,
+        age: u64,
+        score: Option<f64>,
+        tags: Vec<&'static str>,
+        kinds: (Kind, Kind, Kind),
+    }
+
+    #[test]
+    fn test_to_frame() -> Result<(), RespError> {
+        let user = User {
+            name: "alice".to_string(),
+            age: u64::MAX,
+            score: None,
+            tags: vec!["a", "b"],
+            kinds: (Kind::Plain, Kind::Tagged(1), Kind::Pair(-1, true)),
+        };
+
+        let expected: RespFrame = Map::new([
+            (
+                BulkString::from("name").into(),
+                BulkString::from("alice").into(),
+            ),
+            (
+                BulkString::from("age").into(),
+                BigNumber::from(u64::MAX).into(),
+            ),
+            (BulkString::from("score").into(), Null.into()),
+            (
+                BulkString::from("tags").into(),
+                Array::new(vec![
+                    BulkString::from("a").into(),
+                    BulkString::from("b").into(),
+                ])
+                .into(),
+            ),
+            (
+                BulkString::from("kinds").into(),
+                Array::new(vec![
+                    BulkString::from("Plain").into(),
+                    tagged("Tagged", 1.into()),
+                    tagged("Pair", Array::new(vec![(-1).into(), true.into()]).into()),
+                ])
+                .into(),
+            ),
+        ])
+        .into();
+        assert_eq!(to_frame(&user)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_bytes() -> Result<(), RespError> {
+        let command = ("SET", "key", 'v');
+        assert_eq!(
+            to_bytes(&command)?,
+            b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1\r\nv\r\n".to_vec()
+        );
+
+        // keys don't have to be strings
+        let map = BTreeMap::from([(1, 'x'), (2, 'y')]);
+        assert_eq!(
+            to_bytes(&map)?,
+            b"%2\r\n:+1\r\n$1\r\nx\r\n:+2\r\n$1\r\ny\r\n".to_vec()
+        );
+        Ok(())
+    }
+}